@@ -15,6 +15,21 @@ pub enum Error {
     PngEncodingError(#[from] png::EncodingError),
     #[error("not on the main thread")]
     NotMainThread,
+    #[error("tray icon id must not be empty")]
+    EmptyId,
+    #[cfg(target_os = "linux")]
+    #[error("the icon size hint {width}x{height} overflows when multiplied by the scale factor {scale_factor}")]
+    IconSizeHintOverflow {
+        width: u32,
+        height: u32,
+        scale_factor: u32,
+    },
+    #[cfg(feature = "arc")]
+    #[error("the thread that created the tray icon is not running its event loop")]
+    OwnerThreadUnavailable,
+    #[cfg(feature = "arc")]
+    #[error("not on the thread that created the tray icon")]
+    NotOwnerThread,
 }
 
 /// Convenient type alias of Result type for tray-icon.
@@ -34,6 +34,10 @@ pub enum BadIcon {
     },
     /// Produced when underlying OS functionality failed to create the icon
     OsError(io::Error),
+    /// Produced when the supplied data isn't a valid PNG image.
+    PngDecodingError(String),
+    /// Produced when resizing an icon from or to a zero width or height.
+    ZeroDimensions { width: u32, height: u32 },
 }
 
 impl fmt::Display for BadIcon {
@@ -53,6 +57,11 @@ impl fmt::Display for BadIcon {
                 width, height, pixel_count, width_x_height,
             ),
             BadIcon::OsError(e) => write!(f, "OS error when instantiating the icon: {:?}", e),
+            BadIcon::PngDecodingError(e) => write!(f, "Failed to decode the PNG image: {}", e),
+            BadIcon::ZeroDimensions { width, height } => write!(f,
+                "Can't resize an icon from or to a zero width or height ({:?}x{:?}).",
+                width, height,
+            ),
         }
     }
 }
@@ -103,6 +112,104 @@ mod constructors {
         }
     }
 
+    impl RgbaIcon {
+        /// Decodes a PNG image into 32bpp RGBA.
+        pub fn from_png_bytes(bytes: &[u8]) -> Result<Self, BadIcon> {
+            let mut decoder = png::Decoder::new(bytes);
+            decoder.set_transformations(png::Transformations::normalize_to_color8());
+            let mut reader = decoder
+                .read_info()
+                .map_err(|e| BadIcon::PngDecodingError(e.to_string()))?;
+            let mut buf = vec![0; reader.output_buffer_size()];
+            let info = reader
+                .next_frame(&mut buf)
+                .map_err(|e| BadIcon::PngDecodingError(e.to_string()))?;
+            buf.truncate(info.buffer_size());
+
+            let rgba = match info.color_type {
+                png::ColorType::Rgba => buf,
+                png::ColorType::Rgb => buf
+                    .chunks_exact(3)
+                    .flat_map(|p| [p[0], p[1], p[2], u8::MAX])
+                    .collect(),
+                png::ColorType::GrayscaleAlpha => buf
+                    .chunks_exact(2)
+                    .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                    .collect(),
+                png::ColorType::Grayscale => buf.iter().flat_map(|&p| [p, p, p, u8::MAX]).collect(),
+                // palette images are expanded to RGB(A) by `normalize_to_color8`
+                png::ColorType::Indexed => unreachable!(),
+            };
+
+            Self::from_rgba(rgba, info.width, info.height)
+        }
+    }
+
+    impl RgbaIcon {
+        /// Converts the icon to grayscale using the Rec. 709 luma coefficients,
+        /// keeping the alpha channel as is.
+        pub fn to_grayscale(&self) -> Self {
+            let mut rgba = self.rgba.clone();
+            for pixel in rgba.chunks_exact_mut(PIXEL_SIZE) {
+                let y =
+                    0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32;
+                let y = y.round().min(u8::MAX as f32) as u8;
+                pixel[0] = y;
+                pixel[1] = y;
+                pixel[2] = y;
+            }
+            RgbaIcon {
+                rgba,
+                width: self.width,
+                height: self.height,
+            }
+        }
+    }
+
+    impl RgbaIcon {
+        /// Resizes the icon to `width` x `height` using nearest-neighbor sampling.
+        ///
+        /// Returns an error if the icon or the new size is empty, or if the icon wasn't created
+        /// with [`RgbaIcon::from_rgba`] and its pixel count doesn't match its dimensions.
+        pub fn resize(&self, width: u32, height: u32) -> Result<Self, BadIcon> {
+            if self.rgba.len() % PIXEL_SIZE != 0 {
+                return Err(BadIcon::ByteCountNotDivisibleBy4 {
+                    byte_count: self.rgba.len(),
+                });
+            }
+            let pixel_count = self.rgba.len() / PIXEL_SIZE;
+            let width_x_height = self.width as usize * self.height as usize;
+            if pixel_count != width_x_height {
+                return Err(BadIcon::DimensionsVsPixelCount {
+                    width: self.width,
+                    height: self.height,
+                    width_x_height,
+                    pixel_count,
+                });
+            }
+            for (width, height) in [(self.width, self.height), (width, height)] {
+                if width == 0 || height == 0 {
+                    return Err(BadIcon::ZeroDimensions { width, height });
+                }
+            }
+
+            let mut rgba = Vec::with_capacity(width as usize * height as usize * PIXEL_SIZE);
+            for y in 0..height {
+                let src_y = (y as u64 * self.height as u64 / height as u64) as usize;
+                for x in 0..width {
+                    let src_x = (x as u64 * self.width as u64 / width as u64) as usize;
+                    let offset = (src_y * self.width as usize + src_x) * PIXEL_SIZE;
+                    rgba.extend_from_slice(&self.rgba[offset..offset + PIXEL_SIZE]);
+                }
+            }
+            Ok(RgbaIcon {
+                rgba,
+                width,
+                height,
+            })
+        }
+    }
+
     impl NoIcon {
         pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
             // Create the rgba icon anyway to validate the input
@@ -118,9 +225,16 @@ pub struct Icon {
     pub(crate) inner: PlatformIcon,
 }
 
+/// Prints the dimensions of the icon rather than its pixel data.
+///
+/// On Windows and macOS, the dimensions are only known for icons created from RGBA data.
 impl fmt::Debug for Icon {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        fmt::Debug::fmt(&self.inner, formatter)
+        let mut debug = formatter.debug_struct("Icon");
+        if let Some((width, height)) = self.inner.dimensions() {
+            debug.field("width", &width).field("height", &height);
+        }
+        debug.finish()
     }
 }
 
@@ -135,20 +249,93 @@ impl Icon {
         })
     }
 
+    /// Creates a grayscale version of this icon, useful for disabled-state tray icons.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** Only supported for icons created with [`Icon::from_rgba`].
+    /// - **macOS:** Unsupported for icons created with [`Icon::from_ns_image`].
+    pub fn grayscale(&self) -> Result<Self, BadIcon> {
+        Ok(Icon {
+            inner: self.inner.grayscale()?,
+        })
+    }
+
+    /// Creates a checkerboard icon of 8x8 pixel cells alternating between
+    /// `color_a` and `color_b`, given as RGBA.
+    ///
+    /// Useful for testing tray icon creation and placement without an image file.
+    #[cfg(feature = "debug-icons")]
+    pub fn new_checkerboard(width: u32, height: u32, color_a: [u8; 4], color_b: [u8; 4]) -> Self {
+        const CELL_SIZE: u32 = 8;
+
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * PIXEL_SIZE);
+        for y in 0..height {
+            for x in 0..width {
+                let color = if (x / CELL_SIZE + y / CELL_SIZE) % 2 == 0 {
+                    color_a
+                } else {
+                    color_b
+                };
+                rgba.extend_from_slice(&color);
+            }
+        }
+
+        Self::from_rgba(rgba, width, height)
+            .expect("checkerboard icon has valid dimensions and pixel count")
+    }
+
+    /// Creates an icon from the bytes of a PNG image.
+    pub fn from_png_bytes(bytes: &[u8]) -> Result<Self, BadIcon> {
+        let icon = RgbaIcon::from_png_bytes(bytes)?;
+        Self::from_rgba(icon.rgba, icon.width, icon.height)
+    }
+
+    /// Creates an icon from a PNG file.
+    ///
+    /// Returns [`BadIcon::OsError`] if the file can't be read
+    /// and [`BadIcon::PngDecodingError`] if it isn't a valid PNG image.
+    pub fn from_png_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, BadIcon> {
+        let bytes = std::fs::read(path).map_err(BadIcon::OsError)?;
+        Self::from_png_bytes(&bytes)
+    }
+
     /// Create an icon from a file path.
     ///
+    /// The format is detected from the file contents rather than its extension.
+    ///
     /// Specify `size` to load a specific icon size from the file, or `None` to load the default
     /// icon size from the file.
     ///
-    /// In cases where the specified size does not exist in the file, Windows may perform scaling
-    /// to get an icon of the desired size.
-    #[cfg(windows)]
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** Supports `.ico` and PNG files. In cases where the specified size
+    ///   does not exist in an `.ico` file, Windows may perform scaling to get an icon of the desired size.
+    /// - **Linux / macOS:** Only PNG files are supported.
     pub fn from_path<P: AsRef<std::path::Path>>(
         path: P,
         size: Option<(u32, u32)>,
     ) -> Result<Self, BadIcon> {
-        let win_icon = PlatformIcon::from_path(path, size)?;
-        Ok(Icon { inner: win_icon })
+        const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+        let bytes = std::fs::read(path.as_ref()).map_err(BadIcon::OsError)?;
+        if bytes.starts_with(PNG_SIGNATURE) {
+            let mut icon = RgbaIcon::from_png_bytes(&bytes)?;
+            if let Some((width, height)) = size {
+                icon = icon.resize(width, height)?;
+            }
+            return Self::from_rgba(icon.rgba, icon.width, icon.height);
+        }
+
+        #[cfg(windows)]
+        return Ok(Icon {
+            inner: PlatformIcon::from_path(path, size)?,
+        });
+        #[cfg(not(windows))]
+        Err(BadIcon::OsError(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsupported icon format",
+        )))
     }
 
     /// Create an icon from a resource embedded in this executable or library.
@@ -164,6 +351,17 @@ impl Icon {
         Ok(Icon { inner: win_icon })
     }
 
+    /// Create an icon from a resource embedded in this executable or library,
+    /// using the image closest to `size` from the multi-resolution icon resource.
+    ///
+    /// Unlike [`Icon::from_resource`], this avoids scaling a single image when
+    /// the resource contains several sizes, which keeps icons crisp at all DPI scales.
+    #[cfg(windows)]
+    pub fn from_resource_at_size(resource_id: u16, size: (u32, u32)) -> Result<Self, BadIcon> {
+        let win_icon = PlatformIcon::from_resource_at_size(resource_id, size)?;
+        Ok(Icon { inner: win_icon })
+    }
+
     /// This is basically the same as from_resource, but takes a resource name
     /// rather than oridinal id.
     #[cfg(windows)]
@@ -181,4 +379,129 @@ impl Icon {
         let win_icon = PlatformIcon::from_handle(handle as _);
         Icon { inner: win_icon }
     }
+
+    /// Create an icon from an `NSImage`, which is retained by the icon and shown as is,
+    /// without converting it to RGBA data. **macOS only**.
+    ///
+    /// Returns [`BadIcon::OsError`] if `ns_image` is null.
+    ///
+    /// Icons created this way don't support [`Icon::grayscale`]. Like other icons, they are
+    /// [`Send`] and [`Sync`], the image itself is only accessed on the main thread.
+    ///
+    /// # Safety
+    ///
+    /// `ns_image` must be null or a valid pointer to an `NSImage`, which must not be
+    /// mutated from a thread other than the main thread while the icon is alive.
+    #[cfg(target_os = "macos")]
+    pub unsafe fn from_ns_image(ns_image: *mut std::ffi::c_void) -> Result<Self, BadIcon> {
+        Ok(Icon {
+            inner: PlatformIcon::from_ns_image(ns_image)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BadIcon, Icon, RgbaIcon};
+
+    #[test]
+    fn icon_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Icon>();
+        assert_send_sync::<BadIcon>();
+    }
+
+    #[test]
+    fn debug_prints_dimensions() {
+        let icon = Icon::from_rgba(vec![0; 4 * 32 * 16], 32, 16).unwrap();
+        assert_eq!(format!("{icon:?}"), "Icon { width: 32, height: 16 }");
+    }
+
+    #[test]
+    fn decodes_png() {
+        let mut png = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png, 2, 1);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[255, 0, 0, 0, 0, 255]).unwrap();
+        }
+
+        let icon = RgbaIcon::from_png_bytes(&png).unwrap();
+        assert_eq!((icon.width, icon.height), (2, 1));
+        assert_eq!(icon.rgba, vec![255, 0, 0, 255, 0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn from_png_file_distinguishes_errors() {
+        let dir = std::env::temp_dir().join("tray-icon-png-file-tests");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let missing = dir.join("missing.png");
+        let _ = std::fs::remove_file(&missing);
+        assert!(matches!(
+            Icon::from_png_file(&missing),
+            Err(BadIcon::OsError(e)) if e.kind() == std::io::ErrorKind::NotFound
+        ));
+
+        let invalid = dir.join("invalid.png");
+        std::fs::write(&invalid, b"not a png").unwrap();
+        assert!(matches!(
+            Icon::from_png_file(&invalid),
+            Err(BadIcon::PngDecodingError(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn converts_to_grayscale() {
+        let icon = RgbaIcon::from_rgba(vec![255, 0, 0, 255, 10, 20, 30, 128], 2, 1).unwrap();
+        let gray = icon.to_grayscale();
+        assert_eq!(gray.rgba, vec![54, 54, 54, 255, 19, 19, 19, 128]);
+        assert_eq!((gray.width, gray.height), (2, 1));
+    }
+
+    #[test]
+    fn resizes() {
+        let icon = RgbaIcon::from_rgba(vec![1, 1, 1, 1, 2, 2, 2, 2], 2, 1).unwrap();
+        let resized = icon.resize(4, 2).unwrap();
+        assert_eq!((resized.width, resized.height), (4, 2));
+        assert_eq!(
+            resized.rgba,
+            [[1u8; 4], [1; 4], [2; 4], [2; 4], [1; 4], [1; 4], [2; 4], [2; 4]].concat()
+        );
+    }
+
+    #[test]
+    fn resize_rejects_invalid_dimensions() {
+        let empty = RgbaIcon::from_rgba(vec![], 0, 0).unwrap();
+        assert!(matches!(
+            empty.resize(16, 16),
+            Err(BadIcon::ZeroDimensions {
+                width: 0,
+                height: 0
+            })
+        ));
+
+        let icon = RgbaIcon::from_rgba(vec![1; 8], 2, 1).unwrap();
+        assert!(matches!(
+            icon.resize(0, 16),
+            Err(BadIcon::ZeroDimensions {
+                width: 0,
+                height: 16
+            })
+        ));
+
+        let mismatched = RgbaIcon {
+            rgba: vec![1; 4],
+            width: 2,
+            height: 2,
+        };
+        assert!(matches!(
+            mismatched.resize(4, 4),
+            Err(BadIcon::DimensionsVsPixelCount { .. })
+        ));
+    }
 }
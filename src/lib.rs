@@ -89,20 +89,25 @@
 //! }
 //! ```
 
-use std::{
-    cell::RefCell,
-    path::{Path, PathBuf},
-    rc::Rc,
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+#[cfg(not(feature = "arc"))]
+use std::rc::Rc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, PoisonError, RwLock,
 };
 
 use counter::Counter;
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use once_cell::sync::{Lazy, OnceCell};
+use once_cell::sync::Lazy;
 
 mod counter;
 mod error;
 mod icon;
 mod platform_impl;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod tray_icon_id;
 
 pub use self::error::*;
@@ -117,20 +122,65 @@ pub use muda::dpi;
 
 static COUNTER: Counter = Counter::new();
 
+/// Checks that the platform requirements for creating tray icons are met,
+/// returning a descriptive error if they are not.
+///
+/// Creating a [`TrayIcon`] performs the same checks, so calling this is optional
+/// but lets you detect initialization issues early.
+///
+/// ## Platform-specific:
+///
+/// - **Windows:** No-op.
+/// - **Linux:** Returns an error if GTK has not been initialized with `gtk::init()`.
+/// - **macOS:** Returns [`Error::NotMainThread`] if not called on the main thread.
+pub fn init() -> Result<()> {
+    platform_impl::init()
+}
+
+/// Removes the icon files left behind in `temp_dir` by processes that are no longer running,
+/// for example after a crash. Returns the number of removed files.
+///
+/// If `temp_dir` is `None`, the default temp dir is used,
+/// see [`TrayIconAttributes::temp_dir_path`]. **Linux only**.
+#[cfg(target_os = "linux")]
+pub fn cleanup_orphaned_icons(temp_dir: Option<&Path>) -> std::io::Result<u32> {
+    platform_impl::cleanup_orphaned_icons(temp_dir)
+}
+
 /// Attributes to use when creating a tray icon.
+///
+/// With the `serde` feature, the attributes can be serialized to and deserialized from a configuration file,
+/// except for [`icon`](Self::icon) and [`menu`](Self::menu) which must be set separately.
+/// Missing fields use their default value.
+///
+/// The attributes don't implement [`Clone`] because of the [`menu`](Self::menu),
+/// but the other fields can be cloned individually, for example `attrs.icon.clone()`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct TrayIconAttributes {
     /// Tray icon tooltip
     ///
     /// ## Platform-specific:
     ///
     /// - **Linux:** Unsupported.
+    /// - **Windows:** Tooltips longer than 127 UTF-16 code units are truncated.
     pub tooltip: Option<String>,
 
+    /// Tooltip used instead of [`tooltip`](Self::tooltip) on Windows.
+    pub tooltip_for_windows: Option<String>,
+
+    /// Tooltip used instead of [`tooltip`](Self::tooltip) on macOS.
+    pub tooltip_for_macos: Option<String>,
+
+    /// Tooltip used instead of [`tooltip`](Self::tooltip) on Linux, where tooltips are currently unsupported.
+    pub tooltip_for_linux: Option<String>,
+
     /// Tray menu
     ///
     /// ## Platform-specific:
     ///
     /// - **Linux**: once a menu is set, it cannot be removed.
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub menu: Option<Box<dyn menu::ContextMenu>>,
 
     /// Tray icon
@@ -139,17 +189,35 @@ pub struct TrayIconAttributes {
     ///
     /// - **Linux:** Sometimes the icon won't be visible unless a menu is set.
     ///     Setting an empty [`Menu`](crate::menu::Menu) is enough.
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub icon: Option<Icon>,
 
+    /// Tray icon shown instead of [`icon`](Self::icon) while the system uses dark mode.
+    ///
+    /// The shown icon is switched automatically when the system appearance changes.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** Unsupported, [`icon`](Self::icon) is always used.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub dark_mode_icon: Option<Icon>,
+
     /// Tray icon temp dir path. **Linux only**.
     pub temp_dir_path: Option<PathBuf>,
 
-    /// Use the icon as a [template](https://developer.apple.com/documentation/appkit/nsimage/1520017-template?language=objc). **macOS only**.
+    /// Use the icon as a [template](https://developer.apple.com/documentation/appkit/nsimage/1520017-template?language=objc), default is `false`. **macOS only**.
     pub icon_is_template: bool,
 
     /// Whether to show the tray menu on left click or not, default is `true`. **macOS & Windows only**.
     pub menu_on_left_click: bool,
 
+    /// Whether to show the tray menu on right click or not, default is `true`. **macOS & Windows only**.
+    pub menu_on_right_click: bool,
+
+    /// Whether to skip emitting [`TrayIconEvent::Click`] for mouse presses that open
+    /// the tray menu, default is `false`. **macOS only**.
+    pub suppress_click_event_on_menu_open: bool,
+
     /// Tray icon title.
     ///
     /// ## Platform-specific
@@ -161,41 +229,283 @@ pub struct TrayIconAttributes {
     ///   on the user's panel.  This may not be shown in all visualizations.
     /// - **Windows:** Unsupported.
     pub title: Option<String>,
+
+    /// A GUID used to identify the tray icon, allowing Windows to persist its
+    /// state (e.g. whether it is shown in the overflow area) across application restarts. **Windows only**.
+    ///
+    /// The GUID is tied to the path of the executable that registered it,
+    /// so registering the same GUID from a different path will fail.
+    pub guid: Option<[u8; 16]>,
+
+    /// The size the tray icon should be scaled to before being shown. If `None`,
+    /// the icon is used at its original size.
+    ///
+    /// The unit depends on the platform, see below.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Windows:** The size is in physical pixels. The recommended size is given by
+    ///   `GetSystemMetrics(SM_CXSMICON)`, which is 16x16 at 100% scale.
+    /// - **macOS:** The size is in points and the icon is not resampled. By default
+    ///   the icon is scaled to a height of 18 points.
+    /// - **Linux:** The size is in logical pixels, it is multiplied by the scale factor
+    ///   of the primary monitor when the icon is set.
+    pub icon_size_hint: Option<(u32, u32)>,
+
+    /// A name used by the system to save and restore the position of the tray icon
+    /// in the menu bar across application restarts. **macOS only**.
+    ///
+    /// The name must be unique among the tray icons of the application.
+    pub autosave_name: Option<String>,
+
+    /// Whether the tray icon is shown on all Spaces, default is `true`. **macOS only**.
+    pub visible_on_all_spaces: bool,
+
+    /// Space, in points, added on the left and right of the tray icon content. **macOS only**.
+    ///
+    /// The height is fixed by the menu bar, use [`icon_size_hint`](Self::icon_size_hint)
+    /// to shrink the icon vertically instead.
+    pub icon_padding: Option<u8>,
+
+    /// How often, in milliseconds, the cursor position is checked to detect when it left
+    /// the tray icon and emit [`TrayIconEvent::Leave`], default is `15`. **Windows only**.
+    ///
+    /// Windows doesn't notify tray icons when the cursor leaves them, so this is polled
+    /// while the cursor is over the tray icon.
+    pub leave_detection_interval_ms: u32,
+
+    /// Whether the tray icon is shown, default is `true`.
+    ///
+    /// See [`TrayIcon::set_visible`] and [`TrayIcon::is_visible`].
+    pub visible: bool,
 }
 
+/// The default attributes are:
+///
+/// - `None` for all the `Option` attributes.
+/// - `false` for `icon_is_template` and `suppress_click_event_on_menu_open`.
+/// - `true` for `menu_on_left_click` and `menu_on_right_click`, so the menu is shown
+///   on both left and right click on macOS and Windows.
+/// - `true` for `visible_on_all_spaces` and `visible`.
+/// - `15` for `leave_detection_interval_ms`.
 impl Default for TrayIconAttributes {
     fn default() -> Self {
         Self {
             tooltip: None,
+            tooltip_for_windows: None,
+            tooltip_for_macos: None,
+            tooltip_for_linux: None,
             menu: None,
             icon: None,
+            dark_mode_icon: None,
             temp_dir_path: None,
             icon_is_template: false,
             menu_on_left_click: true,
+            menu_on_right_click: true,
+            suppress_click_event_on_menu_open: false,
             title: None,
+            guid: None,
+            icon_size_hint: None,
+            autosave_name: None,
+            visible_on_all_spaces: true,
+            icon_padding: None,
+            leave_detection_interval_ms: 15,
+            visible: true,
+        }
+    }
+}
+
+impl TrayIconAttributes {
+    /// Clones all the attributes except [`menu`](Self::menu), which can't be cloned.
+    fn clone_without_menu(&self) -> Self {
+        Self {
+            tooltip: self.tooltip.clone(),
+            tooltip_for_windows: self.tooltip_for_windows.clone(),
+            tooltip_for_macos: self.tooltip_for_macos.clone(),
+            tooltip_for_linux: self.tooltip_for_linux.clone(),
+            menu: None,
+            icon: self.icon.clone(),
+            dark_mode_icon: self.dark_mode_icon.clone(),
+            temp_dir_path: self.temp_dir_path.clone(),
+            icon_is_template: self.icon_is_template,
+            menu_on_left_click: self.menu_on_left_click,
+            menu_on_right_click: self.menu_on_right_click,
+            suppress_click_event_on_menu_open: self.suppress_click_event_on_menu_open,
+            title: self.title.clone(),
+            guid: self.guid,
+            icon_size_hint: self.icon_size_hint,
+            autosave_name: self.autosave_name.clone(),
+            visible_on_all_spaces: self.visible_on_all_spaces,
+            icon_padding: self.icon_padding,
+            leave_detection_interval_ms: self.leave_detection_interval_ms,
+            visible: self.visible,
+        }
+    }
+
+    /// Fills the attributes that are not set with the ones from `base`.
+    fn inherit(&mut self, base: &TrayIconAttributes) {
+        if self.tooltip.is_none() {
+            self.tooltip.clone_from(&base.tooltip);
+        }
+        if self.tooltip_for_windows.is_none() {
+            self.tooltip_for_windows
+                .clone_from(&base.tooltip_for_windows);
+        }
+        if self.tooltip_for_macos.is_none() {
+            self.tooltip_for_macos.clone_from(&base.tooltip_for_macos);
+        }
+        if self.tooltip_for_linux.is_none() {
+            self.tooltip_for_linux.clone_from(&base.tooltip_for_linux);
+        }
+        if self.icon.is_none() {
+            self.icon.clone_from(&base.icon);
+        }
+        if self.dark_mode_icon.is_none() {
+            self.dark_mode_icon.clone_from(&base.dark_mode_icon);
+        }
+        if self.temp_dir_path.is_none() {
+            self.temp_dir_path.clone_from(&base.temp_dir_path);
+        }
+        if self.title.is_none() {
+            self.title.clone_from(&base.title);
+        }
+        if self.icon_size_hint.is_none() {
+            self.icon_size_hint = base.icon_size_hint;
+        }
+        if self.icon_padding.is_none() {
+            self.icon_padding = base.icon_padding;
+        }
+    }
+
+    /// Overrides the attributes of `self` with the ones set in `other`.
+    ///
+    /// `None` attributes of `other` are treated as "don't override", and so are
+    /// the `bool` and `u32` attributes that have their default value.
+    ///
+    /// This means `merge` can't reset an attribute of `self` back to its default value,
+    /// for example a [`menu_on_left_click`](Self::menu_on_left_click) of `false` in `self`
+    /// stays `false` even if `other` has it set to `true`. Set such attributes directly instead.
+    pub fn merge(&mut self, other: TrayIconAttributes) {
+        let defaults = TrayIconAttributes::default();
+
+        if other.tooltip.is_some() {
+            self.tooltip = other.tooltip;
+        }
+        if other.tooltip_for_windows.is_some() {
+            self.tooltip_for_windows = other.tooltip_for_windows;
+        }
+        if other.tooltip_for_macos.is_some() {
+            self.tooltip_for_macos = other.tooltip_for_macos;
+        }
+        if other.tooltip_for_linux.is_some() {
+            self.tooltip_for_linux = other.tooltip_for_linux;
+        }
+        if other.menu.is_some() {
+            self.menu = other.menu;
+        }
+        if other.icon.is_some() {
+            self.icon = other.icon;
+        }
+        if other.dark_mode_icon.is_some() {
+            self.dark_mode_icon = other.dark_mode_icon;
+        }
+        if other.temp_dir_path.is_some() {
+            self.temp_dir_path = other.temp_dir_path;
+        }
+        if other.icon_is_template != defaults.icon_is_template {
+            self.icon_is_template = other.icon_is_template;
+        }
+        if other.menu_on_left_click != defaults.menu_on_left_click {
+            self.menu_on_left_click = other.menu_on_left_click;
+        }
+        if other.menu_on_right_click != defaults.menu_on_right_click {
+            self.menu_on_right_click = other.menu_on_right_click;
+        }
+        if other.suppress_click_event_on_menu_open != defaults.suppress_click_event_on_menu_open {
+            self.suppress_click_event_on_menu_open = other.suppress_click_event_on_menu_open;
+        }
+        if other.title.is_some() {
+            self.title = other.title;
+        }
+        if other.guid.is_some() {
+            self.guid = other.guid;
+        }
+        if other.icon_size_hint.is_some() {
+            self.icon_size_hint = other.icon_size_hint;
+        }
+        if other.autosave_name.is_some() {
+            self.autosave_name = other.autosave_name;
+        }
+        if other.visible_on_all_spaces != defaults.visible_on_all_spaces {
+            self.visible_on_all_spaces = other.visible_on_all_spaces;
+        }
+        if other.icon_padding.is_some() {
+            self.icon_padding = other.icon_padding;
+        }
+        if other.leave_detection_interval_ms != defaults.leave_detection_interval_ms {
+            self.leave_detection_interval_ms = other.leave_detection_interval_ms;
+        }
+        if other.visible != defaults.visible {
+            self.visible = other.visible;
         }
     }
+
+    /// Replaces [`tooltip`](Self::tooltip) with the tooltip for the current platform, if set.
+    fn use_platform_tooltip(&mut self) {
+        #[cfg(target_os = "windows")]
+        let tooltip = self.tooltip_for_windows.take();
+        #[cfg(target_os = "macos")]
+        let tooltip = self.tooltip_for_macos.take();
+        #[cfg(target_os = "linux")]
+        let tooltip = self.tooltip_for_linux.take();
+
+        if tooltip.is_some() {
+            self.tooltip = tooltip;
+        }
+    }
+
+    /// Deserializes the attributes from a JSON string, see [`TrayIconAttributes`] for
+    /// the fields that are supported.
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> std::result::Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Deserializes the attributes from a TOML string, see [`TrayIconAttributes`] for
+    /// the fields that are supported.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> std::result::Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
 }
 
 /// [`TrayIcon`] builder struct and associated methods.
-#[derive(Default)]
 pub struct TrayIconBuilder {
     id: TrayIconId,
     attrs: TrayIconAttributes,
 }
 
+impl Default for TrayIconBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TrayIconBuilder {
-    /// Creates a new [`TrayIconBuilder`] with default [`TrayIconAttributes`].
+    /// Creates a new [`TrayIconBuilder`] with default [`TrayIconAttributes`]
+    /// and an auto-generated id.
     ///
     /// See [`TrayIcon::new`] for more info.
     pub fn new() -> Self {
         Self {
-            id: TrayIconId(COUNTER.next().to_string()),
+            id: TrayIconId(COUNTER.next().to_string().into()),
             attrs: TrayIconAttributes::default(),
         }
     }
 
     /// Sets the unique id to build the tray icon with.
+    ///
+    /// The id must not be empty, otherwise [`TrayIconBuilder::build`] returns [`Error::EmptyId`].
     pub fn with_id<I: Into<TrayIconId>>(mut self, id: I) -> Self {
         self.id = id.into();
         self
@@ -222,16 +532,48 @@ impl TrayIconBuilder {
         self
     }
 
+    /// Set an icon shown instead of the one set with [`TrayIconBuilder::with_icon`]
+    /// while the system uses dark mode.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** Unsupported.
+    pub fn with_dark_mode_icon(mut self, icon: Icon) -> Self {
+        self.attrs.dark_mode_icon = Some(icon);
+        self
+    }
+
     /// Set a tooltip for this tray icon.
     ///
     /// ## Platform-specific:
     ///
     /// - **Linux:** Unsupported.
+    /// - **Windows:** Tooltips longer than 127 UTF-16 code units are truncated.
     pub fn with_tooltip<S: AsRef<str>>(mut self, s: S) -> Self {
         self.attrs.tooltip = Some(s.as_ref().to_string());
         self
     }
 
+    /// Set a tooltip used instead of the one set with [`TrayIconBuilder::with_tooltip`] on Windows.
+    pub fn with_tooltip_for_windows<S: AsRef<str>>(mut self, s: S) -> Self {
+        self.attrs.tooltip_for_windows = Some(s.as_ref().to_string());
+        self
+    }
+
+    /// Set a tooltip used instead of the one set with [`TrayIconBuilder::with_tooltip`] on macOS.
+    pub fn with_tooltip_for_macos<S: AsRef<str>>(mut self, s: S) -> Self {
+        self.attrs.tooltip_for_macos = Some(s.as_ref().to_string());
+        self
+    }
+
+    /// Set a tooltip used instead of the one set with [`TrayIconBuilder::with_tooltip`] on Linux.
+    ///
+    /// Tooltips are currently unsupported on Linux.
+    pub fn with_tooltip_for_linux<S: AsRef<str>>(mut self, s: S) -> Self {
+        self.attrs.tooltip_for_linux = Some(s.as_ref().to_string());
+        self
+    }
+
     /// Set the tray icon title.
     ///
     /// ## Platform-specific
@@ -268,57 +610,312 @@ impl TrayIconBuilder {
         self
     }
 
+    /// Whether to show the tray menu on right click or not, default is `true`. **macOS & Windows only**.
+    pub fn with_menu_on_right_click(mut self, enable: bool) -> Self {
+        self.attrs.menu_on_right_click = enable;
+        self
+    }
+
+    /// Whether to skip emitting click events for mouse presses that open the tray menu,
+    /// default is `false`. **macOS only**.
+    pub fn with_suppress_click_event_on_menu_open(mut self, suppress: bool) -> Self {
+        self.attrs.suppress_click_event_on_menu_open = suppress;
+        self
+    }
+
+    /// Set a GUID used to identify the tray icon. **Windows only**.
+    ///
+    /// See [`TrayIconAttributes::guid`] for more info.
+    pub fn with_guid(mut self, guid: [u8; 16]) -> Self {
+        self.attrs.guid = Some(guid);
+        self
+    }
+
+    /// Set the size the tray icon should be scaled to before being shown.
+    ///
+    /// See [`TrayIconAttributes::icon_size_hint`] for more info.
+    pub fn with_icon_size_hint(mut self, width: u32, height: u32) -> Self {
+        self.attrs.icon_size_hint = Some((width, height));
+        self
+    }
+
+    /// Set a name used to save and restore the position of the tray icon. **macOS only**.
+    ///
+    /// See [`TrayIconAttributes::autosave_name`] for more info.
+    pub fn with_autosave_name<S: AsRef<str>>(mut self, name: S) -> Self {
+        self.attrs.autosave_name = Some(name.as_ref().to_string());
+        self
+    }
+
+    /// Set the space, in points, added on the left and right of the tray icon content. **macOS only**.
+    ///
+    /// See [`TrayIconAttributes::icon_padding`] for more info.
+    pub fn with_icon_padding(mut self, padding: u8) -> Self {
+        self.attrs.icon_padding = Some(padding);
+        self
+    }
+
+    /// Whether the tray icon is shown on all Spaces, default is `true`. **macOS only**.
+    pub fn with_visible_on_all_spaces(mut self, visible: bool) -> Self {
+        self.attrs.visible_on_all_spaces = visible;
+        self
+    }
+
+    /// How often, in milliseconds, to check whether the cursor left the tray icon,
+    /// default is `15`. **Windows only**.
+    pub fn with_leave_detection_interval_ms(mut self, interval: u32) -> Self {
+        self.attrs.leave_detection_interval_ms = interval;
+        self
+    }
+
+    /// Whether the tray icon is shown once built, default is `true`.
+    pub fn with_visible(mut self, visible: bool) -> Self {
+        self.attrs.visible = visible;
+        self
+    }
+
     /// Access the unique id that will be assigned to the tray icon
     /// this builder will create.
     pub fn id(&self) -> &TrayIconId {
         &self.id
     }
 
+    /// Mutable access to the attributes the tray icon will be created with,
+    /// to configure them directly in between builder calls.
+    pub fn modify(&mut self) -> &mut TrayIconAttributes {
+        &mut self.attrs
+    }
+
     /// Builds and adds a new [`TrayIcon`] to the system tray.
     pub fn build(self) -> Result<TrayIcon> {
         TrayIcon::with_id(self.id, self.attrs)
     }
+
+    /// Builds and adds a new [`TrayIcon`] to the system tray, panicking on failure.
+    ///
+    /// This is a shorthand for `build().expect(..)` meant for examples and prototypes,
+    /// production code should use [`TrayIconBuilder::build`] and handle the error instead.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the tray icon could not be created.
+    #[must_use = "the tray icon is removed when dropped"]
+    #[track_caller]
+    pub fn build_or_panic(self) -> TrayIcon {
+        self.build().expect("failed to build TrayIcon")
+    }
+
+    /// Builds and adds a new [`TrayIcon`] to the system tray, trying again up to `retries` times,
+    /// waiting `delay` between attempts. Returns the first tray icon built successfully or the last error.
+    ///
+    /// This is only meant for transient failures during system startup, for example on Windows
+    /// when the application is started before the taskbar. Persistent errors are not fixed by retrying,
+    /// and the thread is blocked while waiting, so its event loop doesn't run in the meantime.
+    /// Only [`Error::OsError`] is retried, other errors are returned immediately.
+    ///
+    /// The menu is set once the tray icon is built, see [`TrayIcon::set_menu`].
+    pub fn build_with_retry(
+        mut self,
+        retries: u32,
+        delay: std::time::Duration,
+    ) -> Result<TrayIcon> {
+        let menu = self.attrs.menu.take();
+        let mut attempt = 0;
+        let tray_icon = loop {
+            match TrayIcon::with_id(self.id.clone(), self.attrs.clone_without_menu()) {
+                Ok(tray_icon) => break tray_icon,
+                Err(Error::OsError(_)) if attempt < retries => {
+                    attempt += 1;
+                    std::thread::sleep(delay);
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        if menu.is_some() {
+            tray_icon.set_menu(menu)?;
+        }
+        Ok(tray_icon)
+    }
+
+    /// Builds and adds a new [`TrayIcon`] to the system tray, wrapped in an [`Arc`]
+    /// so it can be shared between threads, for example with an event handler.
+    ///
+    /// Calls made from other threads are dispatched to the thread that created the tray icon,
+    /// see [`TrayIcon`].
+    #[cfg(feature = "arc")]
+    pub fn build_shared(self) -> Result<Arc<TrayIcon>> {
+        self.build().map(Arc::new)
+    }
+
+    /// Creates a [`BatchBuilder`] that builds a tray icon for each of the given `attrs`.
+    ///
+    /// Each of the `attrs` inherits the following attributes of this builder unless it
+    /// sets them itself: `tooltip`, `tooltip_for_windows`, `tooltip_for_macos`,
+    /// `tooltip_for_linux`, `icon`, `dark_mode_icon`, `temp_dir_path`, `title`,
+    /// `icon_size_hint` and `icon_padding`.
+    ///
+    /// The id, menu, GUID and autosave name of this builder are not inherited, as they can't
+    /// be shared between tray icons, so each tray icon gets an auto-generated id. The `bool`
+    /// and other non-`Option` attributes, like `menu_on_left_click`, are not inherited either
+    /// since there is no way to tell whether they were set, so they keep their value in `attrs`.
+    pub fn into_batch(self, attrs: impl IntoIterator<Item = TrayIconAttributes>) -> BatchBuilder {
+        BatchBuilder {
+            base: self.attrs,
+            items: attrs.into_iter().collect(),
+        }
+    }
+}
+
+/// Builds several tray icons sharing common attributes,
+/// see [`TrayIconBuilder::into_batch`].
+pub struct BatchBuilder {
+    base: TrayIconAttributes,
+    items: Vec<TrayIconAttributes>,
+}
+
+impl BatchBuilder {
+    /// Builds and adds all the tray icons to the system tray.
+    ///
+    /// If building any of them fails, the tray icons built so far are removed
+    /// and the error is returned.
+    pub fn build_all(self) -> Result<Vec<TrayIcon>> {
+        let base = self.base;
+        self.items
+            .into_iter()
+            .map(|mut attrs| {
+                attrs.inherit(&base);
+                TrayIcon::new(attrs)
+            })
+            .collect()
+    }
 }
 
+#[cfg(not(feature = "arc"))]
+type TrayIconRef = Rc<RefCell<platform_impl::TrayIcon>>;
+#[cfg(feature = "arc")]
+type TrayIconRef = Arc<platform_impl::SharedTrayIcon>;
+
 /// Tray icon struct and associated methods.
 ///
 /// This type is reference-counted and the icon is removed when the last instance is dropped.
+///
+/// With the `arc` feature enabled, this type is [`Send`] and [`Sync`] and calls made from
+/// other threads are dispatched to the thread that created the tray icon and block until
+/// they complete, so that thread must be running its event loop (the GLib main loop on Linux).
+/// If the call can't be dispatched, methods returning a [`Result`] return
+/// `Error::OwnerThreadUnavailable` and the other methods do nothing.
+/// [`TrayIcon::set_menu`] can only be called on the thread that created the tray icon,
+/// as menus can't be sent to other threads.
 #[derive(Clone)]
 pub struct TrayIcon {
     id: TrayIconId,
-    tray: Rc<RefCell<platform_impl::TrayIcon>>,
+    tray: TrayIconRef,
 }
 
 impl TrayIcon {
     /// Builds and adds a new tray icon to the system tray.
     ///
+    /// The tray icon is given an auto-generated id, which is an opaque incrementing
+    /// integer string. It depends on the order tray icons are created in and is not
+    /// stable across restarts, so use [`TrayIcon::with_id`] with an application-defined
+    /// id when the id needs to be persisted, see also [`TrayIconId::is_auto_generated`].
+    ///
     /// ## Platform-specific:
     ///
     /// - **Linux:** Sometimes the icon won't be visible unless a menu is set.
     ///   Setting an empty [`Menu`](crate::menu::Menu) is enough.
     pub fn new(attrs: TrayIconAttributes) -> Result<Self> {
-        let id = TrayIconId(COUNTER.next().to_string());
-        Ok(Self {
-            tray: Rc::new(RefCell::new(platform_impl::TrayIcon::new(
-                id.clone(),
-                attrs,
-            )?)),
-            id,
-        })
+        let id = TrayIconId(COUNTER.next().to_string().into());
+        Self::with_id(id, attrs)
     }
 
     /// Builds and adds a new tray icon to the system tray with the specified Id.
     ///
+    /// Returns [`Error::EmptyId`] if the id is an empty string.
+    ///
     /// See [`TrayIcon::new`] for more info.
-    pub fn with_id<I: Into<TrayIconId>>(id: I, attrs: TrayIconAttributes) -> Result<Self> {
+    pub fn with_id<I: Into<TrayIconId>>(id: I, mut attrs: TrayIconAttributes) -> Result<Self> {
         let id = id.into();
-        Ok(Self {
-            tray: Rc::new(RefCell::new(platform_impl::TrayIcon::new(
-                id.clone(),
-                attrs,
-            )?)),
-            id,
-        })
+        if id.0.is_empty() {
+            return Err(Error::EmptyId);
+        }
+
+        attrs.use_platform_tooltip();
+
+        #[cfg(feature = "log")]
+        log::debug!(
+            "creating tray icon with id `{}` on {} (icon: {}, menu: {}, tooltip: {})",
+            id.0,
+            std::env::consts::OS,
+            attrs.icon.is_some(),
+            attrs.menu.is_some(),
+            attrs.tooltip.is_some(),
+        );
+
+        let tray = platform_impl::TrayIcon::new(id.clone(), attrs)?;
+
+        #[cfg(feature = "log")]
+        log::debug!("created tray icon with id `{}`", id.0);
+
+        TrayIconEvent::send(TrayIconEvent::Created { id: id.clone() });
+
+        #[cfg(not(feature = "arc"))]
+        let tray = Rc::new(RefCell::new(tray));
+        #[cfg(feature = "arc")]
+        let tray = Arc::new(platform_impl::SharedTrayIcon::new(tray));
+        let tray_icon = Self { tray, id };
+
+        #[cfg(target_os = "macos")]
+        tray_icon.recreate_when_lost();
+
+        Ok(tray_icon)
+    }
+
+    /// Re-creates the status item as soon as the system removes it from the menu bar,
+    /// instead of waiting for the tray icon to be updated.
+    #[cfg(target_os = "macos")]
+    fn recreate_when_lost(&self) {
+        let tray = TrayIconRef::downgrade(&self.tray);
+        let _ = self.with_tray(move |platform_tray| {
+            platform_tray.set_status_item_lost_handler(move || {
+                let Some(tray) = tray.upgrade() else {
+                    return;
+                };
+
+                #[cfg(not(feature = "arc"))]
+                let result = match tray.try_borrow_mut() {
+                    Ok(mut tray) => defer_events(|| tray.recreate_if_lost()),
+                    // the tray icon is being updated, which also re-creates it
+                    Err(_) => Ok(()),
+                };
+                #[cfg(feature = "arc")]
+                let result = tray
+                    .with(|tray| tray.recreate_if_lost())
+                    .and_then(|result| result);
+
+                #[cfg(feature = "log")]
+                if let Err(e) = result {
+                    log::warn!("failed to re-create tray icon: {e}");
+                }
+                #[cfg(not(feature = "log"))]
+                let _ = result;
+            })
+        });
+    }
+
+    #[cfg(not(feature = "arc"))]
+    fn with_tray<R>(&self, f: impl FnOnce(&mut platform_impl::TrayIcon) -> R) -> Result<R> {
+        Ok(defer_events(|| f(&mut self.tray.borrow_mut())))
+    }
+
+    /// Runs `f` on the thread that created the tray icon, see [`TrayIcon`].
+    #[cfg(feature = "arc")]
+    fn with_tray<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut platform_impl::TrayIcon) -> R + Send + 'static,
+    ) -> Result<R> {
+        self.tray.with(f)
     }
 
     /// Returns the id associated with this tray icon.
@@ -327,8 +924,18 @@ impl TrayIcon {
     }
 
     /// Set new tray icon. If `None` is provided, it will remove the icon.
-    pub fn set_icon(&self, icon: Option<Icon>) -> Result<()> {
-        self.tray.borrow_mut().set_icon(icon)
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **macOS:** The new icon keeps the template setting of the previous one,
+    ///   see [`TrayIcon::set_icon_as_template`].
+    /// - **macOS / Windows:** The icon set with [`TrayIconBuilder::with_dark_mode_icon`]
+    ///   is still shown instead while the system uses dark mode.
+    ///
+    /// Returns the previous icon, which can be set again later,
+    /// for example to cycle through the frames of an animation.
+    pub fn set_icon(&self, icon: Option<Icon>) -> Result<Option<Icon>> {
+        self.with_tray(move |tray| tray.set_icon(icon))?
     }
 
     /// Set new tray menu.
@@ -336,8 +943,14 @@ impl TrayIcon {
     /// ## Platform-specific:
     ///
     /// - **Linux**: once a menu is set it cannot be removed so `None` has no effect
-    pub fn set_menu(&self, menu: Option<Box<dyn menu::ContextMenu>>) {
-        self.tray.borrow_mut().set_menu(menu)
+    ///
+    /// With the `arc` feature, returns `Error::NotOwnerThread` if not called
+    /// on the thread that created the tray icon.
+    pub fn set_menu(&self, menu: Option<Box<dyn menu::ContextMenu>>) -> Result<()> {
+        #[cfg(not(feature = "arc"))]
+        return self.with_tray(|tray| tray.set_menu(menu))?;
+        #[cfg(feature = "arc")]
+        return self.tray.with_local(|tray| tray.set_menu(menu))?;
     }
 
     /// Sets the tooltip for this tray icon.
@@ -345,8 +958,10 @@ impl TrayIcon {
     /// ## Platform-specific:
     ///
     /// - **Linux:** Unsupported
+    /// - **Windows:** Tooltips longer than 127 UTF-16 code units are truncated.
     pub fn set_tooltip<S: AsRef<str>>(&self, tooltip: Option<S>) -> Result<()> {
-        self.tray.borrow_mut().set_tooltip(tooltip)
+        let tooltip = tooltip.map(|tooltip| tooltip.as_ref().to_string());
+        self.with_tray(move |tray| tray.set_tooltip(tooltip))?
     }
 
     /// Sets the tooltip for this tray icon.
@@ -359,13 +974,37 @@ impl TrayIcon {
     ///   user requests it as it can take up a significant amount of space
     ///   on the user's panel.  This may not be shown in all visualizations.
     /// - **Windows:** Unsupported
-    pub fn set_title<S: AsRef<str>>(&self, title: Option<S>) {
-        self.tray.borrow_mut().set_title(title)
+    pub fn set_title<S: AsRef<str>>(&self, title: Option<S>) -> Result<()> {
+        let title = title.map(|title| title.as_ref().to_string());
+        self.with_tray(move |tray| tray.set_title(title))?
+    }
+
+    /// Sets a styled title for this tray icon. If `None` is provided, it will clear the title. **macOS only**.
+    pub fn set_attributed_title(&self, title: Option<AttributedTitle>) -> Result<()> {
+        #[cfg(target_os = "macos")]
+        return self.with_tray(move |tray| tray.set_attributed_title(title))?;
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = title;
+            Ok(())
+        }
     }
 
     /// Show or hide this tray icon
     pub fn set_visible(&self, visible: bool) -> Result<()> {
-        self.tray.borrow_mut().set_visible(visible)
+        self.with_tray(move |tray| tray.set_visible(visible))?
+    }
+
+    /// Returns whether this tray icon is shown, as set with [`TrayIcon::set_visible`]
+    /// or [`TrayIconAttributes::visible`].
+    ///
+    /// This doesn't account for the tray icon being hidden by the system, for example
+    /// in the overflow area on Windows or behind the notch on macOS.
+    ///
+    /// With the `arc` feature, returns `false` if the call can't be dispatched
+    /// to the thread that created the tray icon.
+    pub fn is_visible(&self) -> bool {
+        self.with_tray(|tray| tray.is_visible()).unwrap_or_default()
     }
 
     /// Sets the tray icon temp dir path. **Linux only**.
@@ -374,7 +1013,10 @@ impl TrayIcon {
     /// be `$XDG_RUNTIME_DIR/tray-icon` or `$TEMP/tray-icon`.
     pub fn set_temp_dir_path<P: AsRef<Path>>(&self, path: Option<P>) {
         #[cfg(target_os = "linux")]
-        self.tray.borrow_mut().set_temp_dir_path(path);
+        {
+            let path = path.map(|path| path.as_ref().to_path_buf());
+            let _ = self.with_tray(move |tray| tray.set_temp_dir_path(path));
+        }
         #[cfg(not(target_os = "linux"))]
         let _ = path;
     }
@@ -382,11 +1024,31 @@ impl TrayIcon {
     /// Set the current icon as a [template](https://developer.apple.com/documentation/appkit/nsimage/1520017-template?language=objc). **macOS only**.
     pub fn set_icon_as_template(&self, is_template: bool) {
         #[cfg(target_os = "macos")]
-        self.tray.borrow_mut().set_icon_as_template(is_template);
+        let _ = self.with_tray(move |tray| tray.set_icon_as_template(is_template));
         #[cfg(not(target_os = "macos"))]
         let _ = is_template;
     }
 
+    /// Set new tray icon and whether it is a [template](https://developer.apple.com/documentation/appkit/nsimage/1520017-template?language=objc)
+    /// in a single update, avoiding the brief flash of the new icon with the previous template setting
+    /// when calling [`TrayIcon::set_icon`] followed by [`TrayIcon::set_icon_as_template`].
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux / Windows:** `is_template` is unsupported, this is the same as [`TrayIcon::set_icon`].
+    pub fn set_icon_and_template(&self, icon: Option<Icon>, is_template: bool) -> Result<()> {
+        #[cfg(target_os = "macos")]
+        return self
+            .with_tray(move |tray| tray.set_icon_and_template(icon, is_template))?
+            .map(|_| ());
+        #[cfg(not(target_os = "macos"))]
+        {
+            self.set_icon(icon)?;
+            self.set_icon_as_template(is_template);
+            Ok(())
+        }
+    }
+
     /// Disable or enable showing the tray menu on left click.
     ///
     /// ## Platform-specific:
@@ -394,18 +1056,67 @@ impl TrayIcon {
     /// - **Linux:** Unsupported.
     pub fn set_show_menu_on_left_click(&self, enable: bool) {
         #[cfg(any(target_os = "macos", target_os = "windows"))]
-        self.tray.borrow_mut().set_show_menu_on_left_click(enable);
+        let _ = self.with_tray(move |tray| tray.set_show_menu_on_left_click(enable));
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        let _ = enable;
+    }
+
+    /// Disable or enable showing the tray menu on right click.
+    ///
+    /// ## Platform-specific:
+    ///
+    /// - **Linux:** Unsupported.
+    pub fn set_show_menu_on_right_click(&self, enable: bool) {
+        #[cfg(any(target_os = "macos", target_os = "windows"))]
+        let _ = self.with_tray(move |tray| tray.set_show_menu_on_right_click(enable));
         #[cfg(not(any(target_os = "macos", target_os = "windows")))]
         let _ = enable;
     }
 
     /// Get tray icon rect.
     ///
+    /// Returns `Ok(None)` if the rect is not available, and an error if the
+    /// underlying OS call failed.
+    ///
     /// ## Platform-specific:
     ///
-    /// - **Linux**: Unsupported.
-    pub fn rect(&self) -> Option<Rect> {
-        self.tray.borrow().rect()
+    /// - **Linux**: Unsupported, always returns `Ok(None)`.
+    /// - **Windows**: Returns `Ok(None)` while the tray icon is hidden.
+    pub fn rect(&self) -> Result<Option<Rect>> {
+        self.with_tray(|tray| tray.rect())?
+    }
+}
+
+/// Prints the id and a summary of the state of the tray icon, for example
+/// `TrayIcon { id: "myapp", visible: true, has_icon: true, has_menu: false }`.
+impl std::fmt::Debug for TrayIcon {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("TrayIcon");
+        debug.field("id", &self.id.0);
+        if let Ok((visible, has_icon, has_menu)) =
+            self.with_tray(|tray| (tray.is_visible(), tray.has_icon(), tray.has_menu()))
+        {
+            debug
+                .field("visible", &visible)
+                .field("has_icon", &has_icon)
+                .field("has_menu", &has_menu);
+        }
+        debug.finish()
+    }
+}
+
+/// Tray icons are compared by their [`TrayIconId`].
+impl PartialEq for TrayIcon {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for TrayIcon {}
+
+impl std::hash::Hash for TrayIcon {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
     }
 }
 
@@ -413,9 +1124,10 @@ impl TrayIcon {
 ///
 /// ## Platform-specific:
 ///
-/// - **Linux**: Unsupported. The event is not emmited even though the icon is shown
+/// - **Linux**: Only [`TrayIconEvent::Created`] and [`TrayIconEvent::Destroyed`] are emitted.
+///   Mouse events are not emmited even though the icon is shown
 ///   and will still show a context menu on right click.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(tag = "type"))]
 #[non_exhaustive]
@@ -429,12 +1141,16 @@ pub enum TrayIconEvent {
         position: dpi::PhysicalPosition<f64>,
         /// Position and size of the tray icon.
         rect: Rect,
+        /// Scale factor of the monitor the tray icon is on,
+        /// to convert `position` and `rect` to logical coordinates.
+        scale_factor: f64,
         /// Mouse button that triggered this event.
         button: MouseButton,
         /// Mouse button state when this event was triggered.
         button_state: MouseButtonState,
     },
-    /// A double click happened on the tray icon. **Windows Only**
+    /// A double click happened on the tray icon. **macOS & Windows Only**
+    #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
     DoubleClick {
         /// Id of the tray icon which triggered this event.
         id: TrayIconId,
@@ -442,10 +1158,14 @@ pub enum TrayIconEvent {
         position: dpi::PhysicalPosition<f64>,
         /// Position and size of the tray icon.
         rect: Rect,
+        /// Scale factor of the monitor the tray icon is on,
+        /// to convert `position` and `rect` to logical coordinates.
+        scale_factor: f64,
         /// Mouse button that triggered this event.
         button: MouseButton,
     },
     /// The mouse entered the tray icon region.
+    #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
     Enter {
         /// Id of the tray icon which triggered this event.
         id: TrayIconId,
@@ -453,8 +1173,12 @@ pub enum TrayIconEvent {
         position: dpi::PhysicalPosition<f64>,
         /// Position and size of the tray icon.
         rect: Rect,
+        /// Scale factor of the monitor the tray icon is on,
+        /// to convert `position` and `rect` to logical coordinates.
+        scale_factor: f64,
     },
     /// The mouse moved over the tray icon region.
+    #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
     Move {
         /// Id of the tray icon which triggered this event.
         id: TrayIconId,
@@ -462,8 +1186,12 @@ pub enum TrayIconEvent {
         position: dpi::PhysicalPosition<f64>,
         /// Position and size of the tray icon.
         rect: Rect,
+        /// Scale factor of the monitor the tray icon is on,
+        /// to convert `position` and `rect` to logical coordinates.
+        scale_factor: f64,
     },
     /// The mouse left the tray icon region.
+    #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
     Leave {
         /// Id of the tray icon which triggered this event.
         id: TrayIconId,
@@ -471,6 +1199,47 @@ pub enum TrayIconEvent {
         position: dpi::PhysicalPosition<f64>,
         /// Position and size of the tray icon.
         rect: Rect,
+        /// Scale factor of the monitor the tray icon is on,
+        /// to convert `position` and `rect` to logical coordinates.
+        scale_factor: f64,
+    },
+    /// The system switched between light and dark mode. **Windows Only**
+    #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+    ThemeChanged {
+        /// Id of the tray icon which received this event.
+        id: TrayIconId,
+        /// Whether the system is now using dark mode.
+        is_dark_mode: bool,
+    },
+    /// The tray icon was removed by the system and has been re-created. **macOS Only**
+    ///
+    /// This can happen when the user resets or customizes the menu bar. The tray icon
+    /// is re-created shortly after, or when it is updated if that happens first, so any
+    /// position-dependent state should be refreshed when this event is received.
+    Recreated {
+        /// Id of the tray icon which was re-created.
+        id: TrayIconId,
+    },
+    /// The tray icon was hidden because the menu bar ran out of space,
+    /// for example behind the notch. **macOS Only**
+    OverflowHidden {
+        /// Id of the tray icon which was hidden.
+        id: TrayIconId,
+    },
+    /// The tray icon is shown again after being hidden, see [`TrayIconEvent::OverflowHidden`]. **macOS Only**
+    OverflowRestored {
+        /// Id of the tray icon which is shown again.
+        id: TrayIconId,
+    },
+    /// The tray icon was created successfully.
+    Created {
+        /// Id of the tray icon which was created.
+        id: TrayIconId,
+    },
+    /// The tray icon is being removed, because the last [`TrayIcon`] referring to it was dropped.
+    Destroyed {
+        /// Id of the tray icon which is removed.
+        id: TrayIconId,
     },
 }
 
@@ -503,6 +1272,23 @@ impl Default for MouseButton {
     }
 }
 
+impl MouseButton {
+    /// Returns the lowercase name of this mouse button, e.g. `"left"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MouseButton::Left => "left",
+            MouseButton::Right => "right",
+            MouseButton::Middle => "middle",
+        }
+    }
+}
+
+impl std::fmt::Display for MouseButton {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 /// Describes a rectangle including position (x - y axis) and size.
 #[derive(Debug, PartialEq, Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -520,22 +1306,222 @@ impl Default for Rect {
     }
 }
 
+impl Rect {
+    /// Creates a rect from its position and size in physical pixels.
+    pub fn new(x: f64, y: f64, width: u32, height: u32) -> Self {
+        Self {
+            size: dpi::PhysicalSize::new(width, height),
+            position: dpi::PhysicalPosition::new(x, y),
+        }
+    }
+
+    /// Converts this rect to logical coordinates using the given `scale_factor`.
+    pub fn to_logical(&self, scale_factor: f64) -> LogicalRect {
+        LogicalRect {
+            size: self.size.to_logical(scale_factor),
+            position: self.position.to_logical(scale_factor),
+        }
+    }
+
+    /// Creates a rect in physical coordinates from a [`LogicalRect`] using the given `scale_factor`.
+    pub fn from_logical(rect: LogicalRect, scale_factor: f64) -> Self {
+        rect.to_physical(scale_factor)
+    }
+}
+
+/// Converts `(x, y, width, height)` to a rect, see [`Rect::new`].
+impl From<(f64, f64, u32, u32)> for Rect {
+    fn from((x, y, width, height): (f64, f64, u32, u32)) -> Self {
+        Self::new(x, y, width, height)
+    }
+}
+
+/// Converts `[x, y, width, height]` to a rect, the size is rounded to whole pixels.
+impl From<[f64; 4]> for Rect {
+    fn from([x, y, width, height]: [f64; 4]) -> Self {
+        Self {
+            size: dpi::PhysicalSize::new(width, height).cast(),
+            position: dpi::PhysicalPosition::new(x, y),
+        }
+    }
+}
+
+/// Describes a rectangle including position (x - y axis) and size in logical coordinates.
+///
+/// See [`Rect`] for the physical coordinates counterpart.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicalRect {
+    pub size: dpi::LogicalSize<f64>,
+    pub position: dpi::LogicalPosition<f64>,
+}
+
+impl Default for LogicalRect {
+    fn default() -> Self {
+        Self {
+            size: dpi::LogicalSize::new(0., 0.),
+            position: dpi::LogicalPosition::new(0., 0.),
+        }
+    }
+}
+
+impl LogicalRect {
+    /// Converts this rect to physical coordinates using the given `scale_factor`.
+    pub fn to_physical(&self, scale_factor: f64) -> Rect {
+        Rect {
+            size: self.size.to_physical(scale_factor),
+            position: self.position.to_physical(scale_factor),
+        }
+    }
+}
+
+/// A styled tray icon title, see [`TrayIcon::set_attributed_title`].
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct AttributedTitle {
+    /// The title text.
+    pub text: String,
+    /// The text color as `(red, green, blue, alpha)` components in the `0.0..=1.0` range.
+    ///
+    /// If `None`, the system menu bar text color is used.
+    pub color: Option<(f64, f64, f64, f64)>,
+    /// The font size in points.
+    ///
+    /// If `None`, the system menu bar font size is used.
+    pub font_size: Option<f64>,
+}
+
+impl AttributedTitle {
+    /// Creates a new [`AttributedTitle`] with the system default color and font size.
+    pub fn new<S: AsRef<str>>(text: S) -> Self {
+        Self {
+            text: text.as_ref().to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the text color as `(red, green, blue, alpha)` components in the `0.0..=1.0` range.
+    pub fn with_color(mut self, color: (f64, f64, f64, f64)) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the font size in points.
+    pub fn with_font_size(mut self, font_size: f64) -> Self {
+        self.font_size = Some(font_size);
+        self
+    }
+}
+
 /// A reciever that could be used to listen to tray events.
 pub type TrayIconEventReceiver = Receiver<TrayIconEvent>;
-type TrayIconEventHandler = Box<dyn Fn(TrayIconEvent) + Send + Sync + 'static>;
+/// A handler for tray events, see [`TrayIconEvent::set_event_handler`].
+pub type TrayIconEventHandler = Arc<dyn Fn(TrayIconEvent) + Send + Sync + 'static>;
 
 static TRAY_CHANNEL: Lazy<(Sender<TrayIconEvent>, TrayIconEventReceiver)> = Lazy::new(unbounded);
-static TRAY_EVENT_HANDLER: OnceCell<Option<TrayIconEventHandler>> = OnceCell::new();
+static TRAY_EVENT_HANDLER: RwLock<Option<TrayIconEventHandler>> = RwLock::new(None);
+/// Held while checking the channel length and sending, so concurrent senders can't exceed it.
+static TRAY_CHANNEL_CAPACITY: Mutex<usize> = Mutex::new(usize::MAX);
+static TRAY_DROPPED_EVENTS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// Events sent on this thread from within [`defer_events`].
+    static DEFERRED_EVENTS: RefCell<Option<Vec<TrayIconEvent>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f`, delaying the events it sends until it returns.
+///
+/// Tray icons are accessed within this, so an event handler accessing a tray icon
+/// doesn't run while it is already borrowed, which would panic (or deadlock with the `arc` feature).
+pub(crate) fn defer_events<R>(f: impl FnOnce() -> R) -> R {
+    struct Deferring;
+
+    impl Drop for Deferring {
+        fn drop(&mut self) {
+            let events = DEFERRED_EVENTS.with(|deferred| deferred.borrow_mut().take());
+            if !std::thread::panicking() {
+                for event in events.into_iter().flatten() {
+                    TrayIconEvent::send(event);
+                }
+            }
+        }
+    }
+
+    if DEFERRED_EVENTS.with(|deferred| deferred.borrow().is_some()) {
+        return f();
+    }
+
+    DEFERRED_EVENTS.with(|deferred| *deferred.borrow_mut() = Some(Vec::new()));
+    let _deferring = Deferring;
+    f()
+}
+
+/// Limits the number of pending events in the channel associated with
+/// [`TrayIconEvent::receiver`] to `capacity`, by default the channel is unbounded.
+///
+/// When the channel is full, new events are dropped and counted in
+/// [`TrayIconEvent::dropped_event_count`]. This prevents the channel from growing
+/// without limit when the application doesn't process events for a while.
+pub fn set_channel_capacity(capacity: usize) {
+    *TRAY_CHANNEL_CAPACITY
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = capacity;
+}
 
 impl TrayIconEvent {
     /// Returns the id of the tray icon which triggered this event.
+    #[deprecated(since = "0.20.0", note = "use `TrayIconEvent::source_id` instead")]
     pub fn id(&self) -> &TrayIconId {
+        self.source_id()
+    }
+
+    /// Returns the id of the tray icon which triggered this event.
+    pub fn source_id(&self) -> &TrayIconId {
         match self {
             TrayIconEvent::Click { id, .. } => id,
             TrayIconEvent::DoubleClick { id, .. } => id,
             TrayIconEvent::Enter { id, .. } => id,
             TrayIconEvent::Move { id, .. } => id,
             TrayIconEvent::Leave { id, .. } => id,
+            TrayIconEvent::ThemeChanged { id, .. } => id,
+            TrayIconEvent::Recreated { id } => id,
+            TrayIconEvent::OverflowHidden { id } => id,
+            TrayIconEvent::OverflowRestored { id } => id,
+            TrayIconEvent::Created { id } => id,
+            TrayIconEvent::Destroyed { id } => id,
+        }
+    }
+
+    /// Returns the position of the mouse for mouse events, and `None` for other events.
+    pub fn position(&self) -> Option<dpi::PhysicalPosition<f64>> {
+        match self {
+            TrayIconEvent::Click { position, .. }
+            | TrayIconEvent::DoubleClick { position, .. }
+            | TrayIconEvent::Enter { position, .. }
+            | TrayIconEvent::Move { position, .. }
+            | TrayIconEvent::Leave { position, .. } => Some(*position),
+            TrayIconEvent::ThemeChanged { .. }
+            | TrayIconEvent::Recreated { .. }
+            | TrayIconEvent::OverflowHidden { .. }
+            | TrayIconEvent::OverflowRestored { .. }
+            | TrayIconEvent::Created { .. }
+            | TrayIconEvent::Destroyed { .. } => None,
+        }
+    }
+
+    /// Returns the rect of the tray icon for mouse events, and `None` for other events.
+    pub fn rect(&self) -> Option<Rect> {
+        match self {
+            TrayIconEvent::Click { rect, .. }
+            | TrayIconEvent::DoubleClick { rect, .. }
+            | TrayIconEvent::Enter { rect, .. }
+            | TrayIconEvent::Move { rect, .. }
+            | TrayIconEvent::Leave { rect, .. } => Some(*rect),
+            TrayIconEvent::ThemeChanged { .. }
+            | TrayIconEvent::Recreated { .. }
+            | TrayIconEvent::OverflowHidden { .. }
+            | TrayIconEvent::OverflowRestored { .. }
+            | TrayIconEvent::Created { .. }
+            | TrayIconEvent::Destroyed { .. } => None,
         }
     }
 
@@ -549,32 +1535,447 @@ impl TrayIconEvent {
         &TRAY_CHANNEL.1
     }
 
+    /// Drains all events currently pending in the channel associated with [`TrayIconEvent::receiver`],
+    /// without blocking.
+    ///
+    /// See [`TrayIconEvent::try_recv_batch`] to limit how many events are drained at once.
+    pub fn try_recv_all() -> Vec<TrayIconEvent> {
+        TRAY_CHANNEL.1.try_iter().collect()
+    }
+
+    /// Drains at most `max` events currently pending in the channel associated with [`TrayIconEvent::receiver`],
+    /// without blocking.
+    ///
+    /// Useful to avoid starving the rest of an event loop when events arrive faster than they are handled.
+    pub fn try_recv_batch(max: usize) -> Vec<TrayIconEvent> {
+        TRAY_CHANNEL.1.try_iter().take(max).collect()
+    }
+
+    /// Returns the number of events pending in the channel associated with [`TrayIconEvent::receiver`].
+    ///
+    /// Useful to monitor whether events are handled fast enough, see also [`set_channel_capacity`].
+    pub fn channel_len() -> usize {
+        TRAY_CHANNEL.1.len()
+    }
+
+    /// Returns the number of events that were dropped because the channel was full,
+    /// see [`set_channel_capacity`].
+    pub fn dropped_event_count() -> u64 {
+        TRAY_DROPPED_EVENTS.load(Ordering::Relaxed)
+    }
+
     /// Set a handler to be called for new events. Useful for implementing custom event sender.
     ///
+    /// The handler can be replaced or removed at any time by calling this function again,
+    /// passing `None` restores sending events to the channel.
+    ///
     /// ## Note
     ///
     /// Calling this function with a `Some` value,
     /// will not send new events to the channel associated with [`TrayIconEvent::receiver`]
-    pub fn set_event_handler<F: Fn(TrayIconEvent) + Send + Sync + 'static>(f: Option<F>) {
-        if let Some(f) = f {
-            let _ = TRAY_EVENT_HANDLER.set(Some(Box::new(f)));
-        } else {
-            let _ = TRAY_EVENT_HANDLER.set(None);
-        }
+    ///
+    /// Events triggered while a [`TrayIcon`] method is running, like [`TrayIconEvent::Recreated`],
+    /// are delivered once it returns, so the handler can safely call [`TrayIcon`] methods.
+    pub fn set_event_handler(handler: Option<TrayIconEventHandler>) {
+        *TRAY_EVENT_HANDLER
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = handler;
+    }
+
+    /// Returns the handler set with [`TrayIconEvent::set_event_handler`], if any.
+    pub fn get_event_handler() -> Option<TrayIconEventHandler> {
+        TRAY_EVENT_HANDLER
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
 
     #[allow(unused)]
     pub(crate) fn send(event: TrayIconEvent) {
-        if let Some(handler) = TRAY_EVENT_HANDLER.get_or_init(|| None) {
+        let Some(event) = DEFERRED_EVENTS.with(|deferred| match &mut *deferred.borrow_mut() {
+            Some(events) => {
+                events.push(event);
+                None
+            }
+            None => Some(event),
+        }) else {
+            return;
+        };
+
+        // The lock is released before calling the handler, so it can replace itself.
+        if let Some(handler) = Self::get_event_handler() {
             handler(event);
         } else {
-            let _ = TRAY_CHANNEL.0.send(event);
+            let capacity = TRAY_CHANNEL_CAPACITY
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if TRAY_CHANNEL.0.len() >= *capacity {
+                TRAY_DROPPED_EVENTS.fetch_add(1, Ordering::Relaxed);
+            } else {
+                let _ = TRAY_CHANNEL.0.send(event);
+            }
         }
     }
 }
 
+/// A filter for selecting specific [`TrayIconEvent`]s from [`TrayIconEvent::receiver`].
+///
+/// Calling several `only_*` methods selects events of any of the given types,
+/// while the other methods narrow down the selection further.
+///
+/// ## Example
+///
+/// ```no_run
+/// # use tray_icon::{TrayIconEventFilter, MouseButton};
+/// for event in TrayIconEventFilter::new()
+///     .only_click()
+///     .with_button(MouseButton::Left)
+///     .with_id("main")
+///     .build()
+/// {
+///     println!("{event:?}");
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct TrayIconEventFilter {
+    kinds: u8,
+    button: Option<MouseButton>,
+    id: Option<TrayIconId>,
+}
+
+impl TrayIconEventFilter {
+    const CLICK: u8 = 1 << 0;
+    const ENTER: u8 = 1 << 1;
+    const MOVE: u8 = 1 << 2;
+    const LEAVE: u8 = 1 << 3;
+
+    /// Creates a new filter that matches all events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select [`TrayIconEvent::Click`] and [`TrayIconEvent::DoubleClick`] events.
+    pub fn only_click(mut self) -> Self {
+        self.kinds |= Self::CLICK;
+        self
+    }
+
+    /// Select [`TrayIconEvent::Enter`] events.
+    pub fn only_enter(mut self) -> Self {
+        self.kinds |= Self::ENTER;
+        self
+    }
+
+    /// Select [`TrayIconEvent::Move`] events.
+    pub fn only_move(mut self) -> Self {
+        self.kinds |= Self::MOVE;
+        self
+    }
+
+    /// Select [`TrayIconEvent::Leave`] events.
+    pub fn only_leave(mut self) -> Self {
+        self.kinds |= Self::LEAVE;
+        self
+    }
+
+    /// Select only click events triggered by `button`.
+    pub fn with_button(mut self, button: MouseButton) -> Self {
+        self.button = Some(button);
+        self
+    }
+
+    /// Select only events triggered by the tray icon with the given id.
+    pub fn with_id<I: Into<TrayIconId>>(mut self, id: I) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Returns whether `event` is selected by this filter.
+    pub fn matches(&self, event: &TrayIconEvent) -> bool {
+        let (kind, button) = match event {
+            TrayIconEvent::Click { button, .. } | TrayIconEvent::DoubleClick { button, .. } => {
+                (Self::CLICK, Some(*button))
+            }
+            TrayIconEvent::Enter { .. } => (Self::ENTER, None),
+            TrayIconEvent::Move { .. } => (Self::MOVE, None),
+            TrayIconEvent::Leave { .. } => (Self::LEAVE, None),
+            TrayIconEvent::ThemeChanged { .. }
+            | TrayIconEvent::Recreated { .. }
+            | TrayIconEvent::OverflowHidden { .. }
+            | TrayIconEvent::OverflowRestored { .. }
+            | TrayIconEvent::Created { .. }
+            | TrayIconEvent::Destroyed { .. } => (0, None),
+        };
+
+        (self.kinds == 0 || self.kinds & kind != 0)
+            && (self.button.is_none() || self.button == button)
+            && self.id.as_ref().map_or(true, |id| id == event.source_id())
+    }
+
+    /// Returns an iterator over the pending events in [`TrayIconEvent::receiver`]
+    /// that are selected by this filter.
+    ///
+    /// Events that are not selected are removed from the channel and discarded.
+    pub fn build(self) -> impl Iterator<Item = TrayIconEvent> {
+        TrayIconEvent::receiver()
+            .try_iter()
+            .filter(move |event| self.matches(event))
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn attributes_default() {
+        use super::*;
+        let attrs = TrayIconAttributes::default();
+        assert!(attrs.tooltip.is_none());
+        assert!(attrs.tooltip_for_windows.is_none());
+        assert!(attrs.tooltip_for_macos.is_none());
+        assert!(attrs.tooltip_for_linux.is_none());
+        assert!(attrs.menu.is_none());
+        assert!(attrs.icon.is_none());
+        assert!(attrs.dark_mode_icon.is_none());
+        assert!(attrs.temp_dir_path.is_none());
+        assert!(!attrs.icon_is_template);
+        assert!(attrs.menu_on_left_click);
+        assert!(attrs.menu_on_right_click);
+        assert!(!attrs.suppress_click_event_on_menu_open);
+        assert!(attrs.title.is_none());
+        assert!(attrs.guid.is_none());
+        assert!(attrs.icon_size_hint.is_none());
+        assert!(attrs.autosave_name.is_none());
+        assert!(attrs.visible_on_all_spaces);
+        assert!(attrs.icon_padding.is_none());
+        assert_eq!(attrs.leave_detection_interval_ms, 15);
+        assert!(attrs.visible);
+    }
+    #[test]
+    fn events_compare_structurally() {
+        use super::*;
+        let event = |button| TrayIconEvent::Click {
+            id: TrayIconId::new("id"),
+            position: dpi::PhysicalPosition::new(1.0, 2.0),
+            rect: Rect::default(),
+            scale_factor: 1.0,
+            button,
+            button_state: MouseButtonState::Up,
+        };
+        assert_eq!(event(MouseButton::Left), event(MouseButton::Left));
+        assert_ne!(event(MouseButton::Left), event(MouseButton::Right));
+        assert_ne!(
+            event(MouseButton::Left),
+            TrayIconEvent::Enter {
+                id: TrayIconId::new("id"),
+                position: dpi::PhysicalPosition::new(1.0, 2.0),
+                rect: Rect::default(),
+                scale_factor: 1.0,
+            }
+        );
+    }
+    #[test]
+    fn defers_events_sent_while_accessing_a_tray_icon() {
+        use super::*;
+        let pending = || DEFERRED_EVENTS.with(|deferred| deferred.borrow().as_ref().map(Vec::len));
+
+        defer_events(|| {
+            TrayIconEvent::send(TrayIconEvent::Recreated {
+                id: TrayIconId::new("deferred"),
+            });
+            defer_events(|| {
+                TrayIconEvent::send(TrayIconEvent::Recreated {
+                    id: TrayIconId::new("deferred"),
+                })
+            });
+            assert_eq!(pending(), Some(2));
+        });
+        assert_eq!(pending(), None);
+    }
+    #[test]
+    fn event_position_and_rect() {
+        use super::*;
+        let rect = Rect {
+            size: dpi::PhysicalSize::new(16, 16),
+            position: dpi::PhysicalPosition::new(10., 20.),
+        };
+        let enter = TrayIconEvent::Enter {
+            id: TrayIconId::new("id"),
+            position: dpi::PhysicalPosition::new(12., 24.),
+            rect,
+            scale_factor: 1.0,
+        };
+        assert_eq!(enter.position(), Some(dpi::PhysicalPosition::new(12., 24.)));
+        assert_eq!(enter.rect(), Some(rect));
+
+        let recreated = TrayIconEvent::Recreated {
+            id: TrayIconId::new("id"),
+        };
+        assert_eq!(recreated.position(), None);
+        assert_eq!(recreated.rect(), None);
+    }
+    #[test]
+    fn rejects_empty_id() {
+        use super::*;
+        assert!(matches!(
+            TrayIconBuilder::new().with_id("").build(),
+            Err(Error::EmptyId)
+        ));
+    }
+    #[test]
+    fn attributes_merge() {
+        use super::*;
+        let mut attrs = TrayIconAttributes {
+            tooltip: Some("base tooltip".into()),
+            title: Some("base title".into()),
+            icon_is_template: true,
+            ..Default::default()
+        };
+        attrs.merge(TrayIconAttributes {
+            title: Some("title".into()),
+            menu_on_left_click: false,
+            ..Default::default()
+        });
+
+        assert_eq!(attrs.tooltip.as_deref(), Some("base tooltip"));
+        assert_eq!(attrs.title.as_deref(), Some("title"));
+        assert!(attrs.icon_is_template);
+        assert!(!attrs.menu_on_left_click);
+
+        // default values can't override a non-default value
+        attrs.merge(TrayIconAttributes::default());
+        assert!(attrs.icon_is_template);
+        assert!(!attrs.menu_on_left_click);
+    }
+    #[test]
+    fn build_with_retry_returns_other_errors_immediately() {
+        use super::*;
+        let result = TrayIconBuilder::new()
+            .with_id("")
+            .build_with_retry(u32::MAX, std::time::Duration::from_secs(60));
+        assert!(matches!(result, Err(Error::EmptyId)));
+    }
+    #[test]
+    fn builder_modify() {
+        use super::*;
+        let mut builder = TrayIconBuilder::new().with_title("title");
+        builder.modify().tooltip = Some("tooltip".into());
+        let builder = builder.with_menu_on_left_click(false);
+
+        assert_eq!(builder.attrs.title.as_deref(), Some("title"));
+        assert_eq!(builder.attrs.tooltip.as_deref(), Some("tooltip"));
+        assert!(!builder.attrs.menu_on_left_click);
+    }
+    #[test]
+    fn platform_tooltip_overrides_tooltip() {
+        use super::*;
+        let mut attrs = TrayIconAttributes {
+            tooltip: Some("tooltip".into()),
+            ..Default::default()
+        };
+        attrs.use_platform_tooltip();
+        assert_eq!(attrs.tooltip.as_deref(), Some("tooltip"));
+
+        let mut attrs = TrayIconAttributes {
+            tooltip: Some("tooltip".into()),
+            tooltip_for_windows: Some("platform tooltip".into()),
+            tooltip_for_macos: Some("platform tooltip".into()),
+            tooltip_for_linux: Some("platform tooltip".into()),
+            ..Default::default()
+        };
+        attrs.use_platform_tooltip();
+        assert_eq!(attrs.tooltip.as_deref(), Some("platform tooltip"));
+    }
+    #[test]
+    fn rect_logical_conversion() {
+        use super::*;
+        let rect = Rect {
+            size: dpi::PhysicalSize::new(32, 48),
+            position: dpi::PhysicalPosition::new(100., 20.),
+        };
+
+        let logical = rect.to_logical(2.);
+        assert_eq!(logical.size, dpi::LogicalSize::new(16., 24.));
+        assert_eq!(logical.position, dpi::LogicalPosition::new(50., 10.));
+        assert_eq!(logical.to_physical(2.), rect);
+        assert_eq!(Rect::from_logical(logical, 2.), rect);
+    }
+    #[test]
+    fn rect_conversions() {
+        use super::*;
+        let rect = Rect::new(10., 20., 16, 24);
+        assert_eq!(rect.position, dpi::PhysicalPosition::new(10., 20.));
+        assert_eq!(rect.size, dpi::PhysicalSize::new(16, 24));
+        assert_eq!(Rect::from((10., 20., 16, 24)), rect);
+        assert_eq!(Rect::from([10., 20., 15.6, 24.]), rect);
+    }
+    #[cfg(feature = "json")]
+    #[test]
+    fn attributes_from_json() {
+        use super::*;
+        let attrs = TrayIconAttributes::from_json(
+            r#"{ "tooltip": "tooltip", "title": "title", "menu_on_left_click": false }"#,
+        )
+        .unwrap();
+
+        assert_eq!(attrs.tooltip.as_deref(), Some("tooltip"));
+        assert_eq!(attrs.title.as_deref(), Some("title"));
+        assert!(!attrs.menu_on_left_click);
+        assert!(attrs.menu_on_right_click);
+        assert!(attrs.icon.is_none());
+        assert!(attrs.menu.is_none());
+    }
+    #[cfg(feature = "json")]
+    #[test]
+    fn attributes_to_json() {
+        use super::*;
+        let attrs = TrayIconAttributes {
+            tooltip: Some("tooltip".into()),
+            icon_size_hint: Some((16, 16)),
+            menu_on_left_click: false,
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&attrs).unwrap();
+        assert!(!json.contains("\"icon\""));
+        assert!(!json.contains("\"menu\""));
+
+        let attrs = TrayIconAttributes::from_json(&json).unwrap();
+        assert_eq!(attrs.tooltip.as_deref(), Some("tooltip"));
+        assert_eq!(attrs.icon_size_hint, Some((16, 16)));
+        assert!(!attrs.menu_on_left_click);
+    }
+    #[test]
+    fn event_filter_matches() {
+        use super::*;
+        let click = TrayIconEvent::Click {
+            id: TrayIconId::new("main"),
+            position: dpi::PhysicalPosition::default(),
+            rect: Rect::default(),
+            scale_factor: 1.0,
+            button: MouseButton::Left,
+            button_state: MouseButtonState::Down,
+        };
+        let enter = TrayIconEvent::Enter {
+            id: TrayIconId::new("other"),
+            position: dpi::PhysicalPosition::default(),
+            rect: Rect::default(),
+            scale_factor: 1.0,
+        };
+
+        assert!(TrayIconEventFilter::new().matches(&click));
+        assert!(TrayIconEventFilter::new().matches(&enter));
+        assert!(TrayIconEventFilter::new().only_click().matches(&click));
+        assert!(!TrayIconEventFilter::new().only_click().matches(&enter));
+        assert!(TrayIconEventFilter::new()
+            .only_click()
+            .only_enter()
+            .matches(&enter));
+        assert!(!TrayIconEventFilter::new()
+            .with_button(MouseButton::Right)
+            .matches(&click));
+        assert!(TrayIconEventFilter::new().with_id("main").matches(&click));
+        assert!(!TrayIconEventFilter::new().with_id("main").matches(&enter));
+    }
 
     #[cfg(feature = "serde")]
     #[test]
@@ -586,6 +1987,7 @@ mod tests {
             id: TrayIconId::new("id"),
             position: dpi::PhysicalPosition::default(),
             rect: Rect::default(),
+            scale_factor: 1.0,
         };
 
         let value = serde_json::to_value(&event).unwrap();
@@ -600,6 +2002,7 @@ mod tests {
                     "x": 0.0,
                     "y": 0.0,
                 },
+                "scaleFactor": 1.0,
                 "rect": {
                     "size": {
                         "width": 0,
@@ -613,4 +2016,21 @@ mod tests {
             })
         )
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_serializes_scale_factor_in_camel_case() {
+        use super::*;
+        let event = TrayIconEvent::Enter {
+            id: TrayIconId::new("id"),
+            position: dpi::PhysicalPosition::default(),
+            rect: Rect::default(),
+            scale_factor: 2.0,
+        };
+
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["type"], "Enter");
+        assert_eq!(value["scaleFactor"], 2.0);
+        assert!(value.get("scale_factor").is_none());
+    }
 }
@@ -2,11 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
-use std::{fs::File, io::BufWriter, path::Path};
+use std::{fs::File, io, io::BufWriter, path::Path};
 
-use crate::icon::BadIcon;
+use crate::icon::{BadIcon, RgbaIcon};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PlatformIcon {
     rgba: Vec<u8>,
     width: i32,
@@ -15,8 +15,41 @@ pub struct PlatformIcon {
 
 impl PlatformIcon {
     pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
+        let icon = RgbaIcon::from_rgba(rgba, width, height)?;
         Ok(Self {
-            rgba,
+            rgba: icon.rgba,
+            width: width as i32,
+            height: height as i32,
+        })
+    }
+
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        Some((self.width as u32, self.height as u32))
+    }
+
+    pub fn grayscale(&self) -> Result<Self, BadIcon> {
+        let gray = RgbaIcon {
+            rgba: self.rgba.clone(),
+            width: self.width as u32,
+            height: self.height as u32,
+        }
+        .to_grayscale();
+        Self::from_rgba(gray.rgba, gray.width, gray.height)
+    }
+
+    pub fn resize(&self, width: u32, height: u32) -> io::Result<Self> {
+        let resized = RgbaIcon {
+            rgba: self.rgba.clone(),
+            width: self.width as u32,
+            height: self.height as u32,
+        }
+        .resize(width, height)
+        .map_err(|e| match e {
+            BadIcon::OsError(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        })?;
+        Ok(Self {
+            rgba: resized.rgba,
             width: width as i32,
             height: height as i32,
         })
@@ -36,3 +69,20 @@ impl PlatformIcon {
         Ok(())
     }
 }
+
+#[test]
+fn write_to_png_roundtrips_pixels() {
+    let rgba = vec![
+        255, 0, 0, 255, 0, 255, 0, 128, //
+        0, 0, 255, 0, 255, 255, 255, 255,
+    ];
+    let icon = PlatformIcon::from_rgba(rgba.clone(), 2, 2).unwrap();
+
+    let path = std::env::temp_dir().join(format!("tray-icon-test-{}.png", std::process::id()));
+    icon.write_to_png(&path).unwrap();
+    let decoded = RgbaIcon::from_png_bytes(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!((decoded.width, decoded.height), (2, 2));
+    assert_eq!(decoded.rgba, rgba);
+}
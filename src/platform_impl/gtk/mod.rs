@@ -9,27 +9,46 @@ use crate::icon::Icon;
 pub(crate) use icon::PlatformIcon;
 
 use crate::{TrayIconAttributes, TrayIconId, COUNTER};
+use gtk::gdk::prelude::MonitorExt;
 use libappindicator::{AppIndicator, AppIndicatorStatus};
 
 pub struct TrayIcon {
-    id: u32,
+    id: TrayIconId,
+    /// Unique in the process, used to name the icon files.
+    icon_file_id: u32,
     indicator: AppIndicator,
     temp_dir_path: Option<PathBuf>,
     path: PathBuf,
     counter: u32,
     menu: Option<Box<dyn muda::ContextMenu>>,
+    icon_size_hint: Option<(u32, u32)>,
+    /// The currently shown icon, used to skip updates that don't change it.
+    icon: Option<PlatformIcon>,
+    visible: bool,
 }
 
 impl TrayIcon {
-    pub fn new(_id: TrayIconId, attrs: TrayIconAttributes) -> crate::Result<Self> {
-        let id = COUNTER.next();
-        let mut indicator = AppIndicator::new("tray-icon tray app", "");
-        indicator.set_status(AppIndicatorStatus::Active);
+    pub fn new(id: TrayIconId, attrs: TrayIconAttributes) -> crate::Result<Self> {
+        init()?;
+
+        // Each indicator needs a unique id, otherwise some desktop environments will replace
+        // the previous indicator with the new one. Desktop environments may also use this id to
+        // persist the indicator state, so it is derived from the tray icon id, which stays the
+        // same across runs when set with `TrayIcon::with_id`.
+        let mut indicator = AppIndicator::new(&format!("tray-icon-{}", id.0), "");
 
-        let (parent_path, icon_path) = temp_icon_path(attrs.temp_dir_path.as_ref(), id, 0)?;
+        let icon_file_id = COUNTER.next();
+        indicator.set_status(if attrs.visible {
+            AppIndicatorStatus::Active
+        } else {
+            AppIndicatorStatus::Passive
+        });
+
+        let (parent_path, icon_path) =
+            temp_icon_path(attrs.temp_dir_path.as_ref(), icon_file_id, 0)?;
 
-        if let Some(icon) = attrs.icon {
-            icon.inner.write_to_png(&icon_path)?;
+        if let Some(icon) = &attrs.icon {
+            write_icon_to_png(icon, attrs.icon_size_hint, &icon_path)?;
         }
 
         indicator.set_icon_theme_path(&parent_path.to_string_lossy());
@@ -45,23 +64,31 @@ impl TrayIcon {
 
         Ok(Self {
             id,
+            icon_file_id,
             indicator,
             path: icon_path,
             temp_dir_path: attrs.temp_dir_path,
             counter: 0,
             menu: attrs.menu,
+            icon_size_hint: attrs.icon_size_hint,
+            icon: attrs.icon.map(|icon| icon.inner),
+            visible: attrs.visible,
         })
     }
-    pub fn set_icon(&mut self, icon: Option<Icon>) -> crate::Result<()> {
+    pub fn set_icon(&mut self, icon: Option<Icon>) -> crate::Result<Option<Icon>> {
+        if icon.as_ref().map(|icon| &icon.inner) == self.icon.as_ref() {
+            return Ok(self.icon.clone().map(|inner| Icon { inner }));
+        }
+
         let _ = std::fs::remove_file(&self.path);
 
         self.counter += 1;
 
         let (parent_path, icon_path) =
-            temp_icon_path(self.temp_dir_path.as_ref(), self.id, self.counter)?;
+            temp_icon_path(self.temp_dir_path.as_ref(), self.icon_file_id, self.counter)?;
 
-        if let Some(icon) = icon {
-            icon.inner.write_to_png(&icon_path)?;
+        if let Some(icon) = &icon {
+            write_icon_to_png(icon, self.icon_size_hint, &icon_path)?;
         }
 
         self.indicator
@@ -69,42 +96,62 @@ impl TrayIcon {
         self.indicator
             .set_icon_full(&icon_path.to_string_lossy(), "tray icon");
         self.path = icon_path;
+        let previous = std::mem::replace(&mut self.icon, icon.map(|icon| icon.inner));
 
-        Ok(())
+        Ok(previous.map(|inner| Icon { inner }))
     }
 
-    pub fn set_menu(&mut self, menu: Option<Box<dyn crate::menu::ContextMenu>>) {
+    pub fn set_menu(
+        &mut self,
+        menu: Option<Box<dyn crate::menu::ContextMenu>>,
+    ) -> crate::Result<()> {
         if let Some(menu) = &menu {
             self.indicator.set_menu(&mut menu.gtk_context_menu());
         }
         self.menu = menu;
+        Ok(())
     }
 
     pub fn set_tooltip<S: AsRef<str>>(&mut self, _tooltip: Option<S>) -> crate::Result<()> {
         Ok(())
     }
 
-    pub fn set_title<S: AsRef<str>>(&mut self, title: Option<S>) {
+    pub fn set_title<S: AsRef<str>>(&mut self, title: Option<S>) -> crate::Result<()> {
         self.indicator
             .set_label(title.as_ref().map(|t| t.as_ref()).unwrap_or(""), "");
+        Ok(())
     }
 
     pub fn set_visible(&mut self, visible: bool) -> crate::Result<()> {
+        // unlike a macOS status item, the indicator is only hidden, so it keeps its menu
         if visible {
             self.indicator.set_status(AppIndicatorStatus::Active);
         } else {
             self.indicator.set_status(AppIndicatorStatus::Passive);
         }
+        self.visible = visible;
 
         Ok(())
     }
 
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn has_icon(&self) -> bool {
+        self.icon.is_some()
+    }
+
+    pub fn has_menu(&self) -> bool {
+        self.menu.is_some()
+    }
+
     pub fn set_temp_dir_path<P: AsRef<Path>>(&mut self, path: Option<P>) {
         self.temp_dir_path = path.map(|p| p.as_ref().to_path_buf());
     }
 
-    pub fn rect(&self) -> Option<crate::Rect> {
-        None
+    pub fn rect(&self) -> crate::Result<Option<crate::Rect>> {
+        Ok(None)
     }
 }
 
@@ -112,30 +159,188 @@ impl Drop for TrayIcon {
     fn drop(&mut self) {
         self.indicator.set_status(AppIndicatorStatus::Passive);
         let _ = std::fs::remove_file(&self.path);
+        super::send_destroyed(&self.id);
+    }
+}
+
+#[cfg(feature = "arc")]
+impl TrayIcon {
+    pub(crate) fn owner_thread(&self) -> OwnerThread {}
+}
+
+/// Tray icons on Linux are always owned by the GTK main thread.
+#[cfg(feature = "arc")]
+pub(crate) type OwnerThread = ();
+
+#[cfg(feature = "arc")]
+pub(crate) fn is_on_owner_thread(_owner: OwnerThread) -> bool {
+    gtk::is_initialized_main_thread()
+}
+
+/// Runs `f` on the GTK main thread and blocks until it returns,
+/// which requires the GTK main thread to be running a GLib main loop.
+#[cfg(feature = "arc")]
+pub(crate) fn run_on_owner_thread<R: Send + 'static>(
+    _owner: OwnerThread,
+    f: impl FnOnce() -> R + Send + 'static,
+) -> crate::Result<R> {
+    if gtk::is_initialized_main_thread() {
+        return Ok(f());
+    }
+
+    let context = glib::MainContext::default();
+    // A running main loop owns the context, so if it can be acquired here
+    // nothing would run the task and waiting for it would block forever.
+    if context.acquire().is_ok() {
+        return Err(crate::Error::OwnerThreadUnavailable);
+    }
+
+    let (tx, rx) = crossbeam_channel::bounded(1);
+    context.invoke(move || {
+        let _ = tx.send(f());
+    });
+
+    // the sender is dropped without sending if the task is dropped without running
+    rx.recv().map_err(|_| crate::Error::OwnerThreadUnavailable)
+}
+
+pub fn init() -> crate::Result<()> {
+    if !gtk::is_initialized() {
+        return Err(crate::Error::OsError(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "GTK is not initialized, make sure to call `gtk::init()` before creating a tray icon",
+        )));
+    }
+
+    Ok(())
+}
+
+/// Writes the icon to `path`, scaling it to `size_hint` first if provided.
+///
+/// The size hint is multiplied by the scale factor of the display,
+/// so the icon stays crisp on HiDPI displays.
+fn write_icon_to_png(icon: &Icon, size_hint: Option<(u32, u32)>, path: &Path) -> crate::Result<()> {
+    match size_hint {
+        Some((width, height)) => {
+            let scale_factor = scale_factor();
+            let (Some(scaled_width), Some(scaled_height)) = (
+                width.checked_mul(scale_factor),
+                height.checked_mul(scale_factor),
+            ) else {
+                return Err(crate::Error::IconSizeHintOverflow {
+                    width,
+                    height,
+                    scale_factor,
+                });
+            };
+            icon.inner
+                .resize(scaled_width, scaled_height)?
+                .write_to_png(path)
+        }
+        None => icon.inner.write_to_png(path),
     }
 }
 
-/// Generates an icon path in one of the following dirs:
+/// Returns the scale factor of the primary monitor, or `1` if there is no display.
+///
+/// Wayland has no primary monitor, so the highest scale factor of all monitors is used
+/// instead, since the indicator may be shown on any of them.
+fn scale_factor() -> u32 {
+    let Some(display) = gtk::gdk::Display::default() else {
+        return 1;
+    };
+
+    let scale_factor = match display.primary_monitor() {
+        Some(monitor) => monitor.scale_factor(),
+        None => (0..display.n_monitors())
+            .filter_map(|i| display.monitor(i))
+            .map(|monitor| monitor.scale_factor())
+            .max()
+            .unwrap_or(1),
+    };
+    scale_factor.max(1) as u32
+}
+
+/// Returns the dir icons are written to, which is one of the following:
 /// 1. If `temp_icon_dir` is `Some` use that.
 /// 2. `$XDG_RUNTIME_DIR/tray-icon`
 /// 3. `/tmp/tray-icon`
+fn temp_icon_dir(temp_icon_dir: Option<&Path>) -> PathBuf {
+    match temp_icon_dir {
+        Some(path) => path.to_path_buf(),
+        None => dirs::runtime_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("tray-icon"),
+    }
+}
+
+/// Generates an icon path in the dir returned by [`temp_icon_dir`].
+///
+/// The file name contains the id of the current process
+/// so [`cleanup_orphaned_icons`] can tell which files are no longer in use.
 fn temp_icon_path(
     temp_icon_dir: Option<&PathBuf>,
     id: u32,
     counter: u32,
 ) -> std::io::Result<(PathBuf, PathBuf)> {
-    let parent_path = match temp_icon_dir.as_ref() {
-        Some(path) => path.to_path_buf(),
-        None => dirs::runtime_dir()
-            .unwrap_or_else(std::env::temp_dir)
-            .join("tray-icon"),
-    };
+    let parent_path = self::temp_icon_dir(temp_icon_dir.map(|p| p.as_path()));
 
     std::fs::create_dir_all(&parent_path)?;
-    let icon_path = parent_path.join(format!("tray-icon-{}-{}.png", id, counter));
+    let icon_path = parent_path.join(format!(
+        "tray-icon-{}-{}-{}.png",
+        std::process::id(),
+        id,
+        counter
+    ));
     Ok((parent_path, icon_path))
 }
 
+pub fn cleanup_orphaned_icons(temp_dir: Option<&Path>) -> std::io::Result<u32> {
+    let entries = match std::fs::read_dir(self::temp_icon_dir(temp_dir)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let mut count = 0;
+    for entry in entries {
+        let path = entry?.path();
+        let Some(pid) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(temp_icon_pid)
+        else {
+            continue;
+        };
+
+        if !Path::new("/proc").join(pid.to_string()).exists() {
+            match std::fs::remove_file(&path) {
+                Ok(()) => count += 1,
+                // removed by another process in the meantime
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// Returns the process id from an icon file name generated by [`temp_icon_path`],
+/// or `None` if the file name doesn't have the exact `tray-icon-{pid}-{id}-{counter}.png` shape.
+fn temp_icon_pid(file_name: &str) -> Option<u32> {
+    let name = file_name.strip_prefix("tray-icon-")?.strip_suffix(".png")?;
+    let segments = name
+        .split('-')
+        .map(|segment| segment.parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    match segments[..] {
+        [pid, _id, _counter] => Some(pid),
+        _ => None,
+    }
+}
+
 #[test]
 fn temp_icon_path_preference_order() {
     let runtime_dir = option_env!("XDG_RUNTIME_DIR");
@@ -154,3 +359,30 @@ fn temp_icon_path_preference_order() {
 
     assert_eq!(dir3, PathBuf::from("/tmp/tray-icon"));
 }
+
+#[test]
+fn cleanup_orphaned_icons_removes_dead_processes_icons() {
+    let dir = std::env::temp_dir().join("tray-icon-cleanup-tests");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let (_, alive) = temp_icon_path(Some(&dir), 0, 0).unwrap();
+    // pids are capped at 2^22 on linux
+    let orphan = dir.join(format!("tray-icon-{}-0-0.png", u32::MAX));
+    let unrelated = dir.join("unrelated.png");
+    // files without a pid, from older versions or not created by this crate
+    let legacy = dir.join(format!("tray-icon-{}-0.png", u32::MAX));
+    let user = dir.join("tray-icon-3-foo.png");
+    for path in [&alive, &orphan, &unrelated, &legacy, &user] {
+        std::fs::write(path, []).unwrap();
+    }
+
+    assert_eq!(cleanup_orphaned_icons(Some(&dir)).unwrap(), 1);
+    assert!(alive.exists());
+    assert!(!orphan.exists());
+    assert!(unrelated.exists());
+    assert!(legacy.exists());
+    assert!(user.exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
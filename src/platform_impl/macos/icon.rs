@@ -2,34 +2,145 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
-use crate::icon::{BadIcon, RgbaIcon};
-use std::io::Cursor;
+use std::{ffi::c_void, io, sync::Arc};
 
+use core_graphics::{
+    base::{kCGImageAlphaLast, kCGRenderingIntentDefault},
+    color_space::CGColorSpace,
+    data_provider::CGDataProvider,
+    image::CGImage,
+};
+use foreign_types::ForeignType;
+use objc2::{
+    encode::{Encode, Encoding},
+    msg_send_id,
+    rc::Retained,
+    ClassType,
+};
+use objc2_app_kit::NSImage;
+use objc2_foundation::{MainThreadMarker, NSSize};
+
+use crate::icon::{BadIcon, RgbaIcon, PIXEL_SIZE};
+
+#[derive(Debug, Clone)]
+pub enum PlatformIcon {
+    Rgba(RgbaIcon),
+    /// An image provided by the application, see [`Icon::from_ns_image`](crate::Icon::from_ns_image).
+    NsImage(MainThreadImage),
+}
+
+/// An `NSImage` which can be moved to other threads with the icon, but is only accessed
+/// on the main thread, so icons stay `Send` and `Sync` like on the other platforms.
 #[derive(Debug, Clone)]
-pub struct PlatformIcon(RgbaIcon);
+pub struct MainThreadImage(Retained<NSImage>);
+
+// SAFETY: the image is only accessed through `get`, which requires a `MainThreadMarker`.
+// Retaining and releasing it, when cloning and dropping, is thread-safe.
+unsafe impl Send for MainThreadImage {}
+unsafe impl Sync for MainThreadImage {}
+
+impl MainThreadImage {
+    fn get(&self, _mtm: MainThreadMarker) -> &NSImage {
+        &self.0
+    }
+}
+
+/// A `CGImageRef`, encoded as expected by `-[NSImage initWithCGImage:size:]`.
+#[repr(transparent)]
+struct CGImageRef(*const c_void);
+
+unsafe impl Encode for CGImageRef {
+    const ENCODING: Encoding = Encoding::Pointer(&Encoding::Struct("CGImage", &[]));
+}
 
 impl PlatformIcon {
     pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
-        Ok(PlatformIcon(RgbaIcon::from_rgba(rgba, width, height)?))
+        Ok(PlatformIcon::Rgba(RgbaIcon::from_rgba(
+            rgba, width, height,
+        )?))
     }
 
-    pub fn get_size(&self) -> (u32, u32) {
-        (self.0.width, self.0.height)
+    /// # Safety
+    ///
+    /// `ns_image` must be null or a valid pointer to an `NSImage`.
+    pub unsafe fn from_ns_image(ns_image: *mut c_void) -> Result<Self, BadIcon> {
+        Retained::retain(ns_image.cast::<NSImage>())
+            .map(|image| PlatformIcon::NsImage(MainThreadImage(image)))
+            .ok_or_else(|| {
+                BadIcon::OsError(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the NSImage pointer is null",
+                ))
+            })
     }
 
-    pub fn to_png(&self) -> crate::Result<Vec<u8>> {
-        let mut png = Vec::new();
+    pub fn grayscale(&self) -> Result<Self, BadIcon> {
+        match self {
+            PlatformIcon::Rgba(icon) => Ok(PlatformIcon::Rgba(icon.to_grayscale())),
+            PlatformIcon::NsImage(_) => Err(BadIcon::OsError(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "icons created from an NSImage can't be converted to grayscale",
+            ))),
+        }
+    }
 
-        {
-            let mut encoder =
-                png::Encoder::new(Cursor::new(&mut png), self.0.width as _, self.0.height as _);
-            encoder.set_color(png::ColorType::Rgba);
-            encoder.set_depth(png::BitDepth::Eight);
+    /// Returns the size in pixels, or in points for an `NSImage`.
+    pub fn get_size(&self, mtm: MainThreadMarker) -> (u32, u32) {
+        match self {
+            PlatformIcon::Rgba(icon) => (icon.width, icon.height),
+            PlatformIcon::NsImage(image) => {
+                let size = unsafe { image.get(mtm).size() };
+                (size.width.round() as u32, size.height.round() as u32)
+            }
+        }
+    }
 
-            let mut writer = encoder.write_header()?;
-            writer.write_image_data(&self.0.rgba)?;
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match self {
+            PlatformIcon::Rgba(icon) => Some((icon.width, icon.height)),
+            PlatformIcon::NsImage(_) => None,
         }
+    }
+
+    /// Returns the `NSImage` to show, created directly from the RGBA data without going through
+    /// an encoded image format, or a copy of the `NSImage` the icon was created from,
+    /// so changing its size doesn't affect the application's image.
+    pub fn to_ns_image(&self, mtm: MainThreadMarker) -> crate::Result<Retained<NSImage>> {
+        let icon = match self {
+            PlatformIcon::Rgba(icon) => icon,
+            PlatformIcon::NsImage(image) => {
+                return Ok(unsafe { msg_send_id![image.get(mtm), copy] })
+            }
+        };
+        let (width, height) = (icon.width as usize, icon.height as usize);
+
+        let provider = CGDataProvider::from_buffer(Arc::new(icon.rgba.clone()));
+        let cg_image = CGImage::new(
+            width,
+            height,
+            8,
+            PIXEL_SIZE * 8,
+            width * PIXEL_SIZE,
+            &CGColorSpace::create_device_rgb(),
+            kCGImageAlphaLast,
+            &provider,
+            false,
+            kCGRenderingIntentDefault,
+        );
 
-        Ok(png)
+        let size = NSSize::new(width as f64, height as f64);
+        let image: Option<Retained<NSImage>> = unsafe {
+            msg_send_id![
+                NSImage::alloc(),
+                initWithCGImage: CGImageRef(cg_image.as_ptr() as *const c_void),
+                size: size
+            ]
+        };
+        image.ok_or_else(|| {
+            crate::Error::OsError(io::Error::new(
+                io::ErrorKind::Other,
+                "failed to create NSImage from CGImage",
+            ))
+        })
     }
 }
@@ -4,21 +4,30 @@
 
 mod icon;
 use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
 
 use core_graphics::display::CGDisplay;
 use objc2::rc::Retained;
-use objc2::{declare_class, msg_send, msg_send_id, mutability, ClassType, DeclaredClass};
+use objc2::runtime::AnyObject;
+use objc2::{declare_class, msg_send, msg_send_id, mutability, sel, ClassType, DeclaredClass};
 use objc2_app_kit::{
-    NSCellImagePosition, NSEvent, NSImage, NSMenu, NSStatusBar, NSStatusItem, NSTrackingArea,
-    NSTrackingAreaOptions, NSVariableStatusItemLength, NSView, NSWindow,
+    NSAppearance, NSAppearanceNameAqua, NSAppearanceNameDarkAqua, NSButton, NSCellImagePosition,
+    NSColor, NSEvent, NSFont, NSFontAttributeName, NSForegroundColorAttributeName, NSMenu,
+    NSStatusBar, NSStatusItem, NSTrackingArea, NSTrackingAreaOptions, NSVariableStatusItemLength,
+    NSView, NSWindow, NSWindowCollectionBehavior, NSWindowDidChangeOcclusionStateNotification,
+    NSWindowOcclusionState,
+};
+use objc2_foundation::{
+    CGPoint, CGRect, CGSize, MainThreadMarker, NSArray, NSAttributedString, NSAttributedStringKey,
+    NSDictionary, NSNotification, NSNotificationCenter, NSSize, NSString, NSTimer,
 };
-use objc2_foundation::{CGPoint, CGRect, CGSize, MainThreadMarker, NSData, NSSize, NSString};
 
 pub(crate) use self::icon::PlatformIcon;
 use crate::Error;
 use crate::{
-    icon::Icon, menu, MouseButton, MouseButtonState, Rect, TrayIconAttributes, TrayIconEvent,
-    TrayIconId,
+    icon::Icon, menu, AttributedTitle, MouseButton, MouseButtonState, Rect, TrayIconAttributes,
+    TrayIconEvent, TrayIconId,
 };
 
 pub struct TrayIcon {
@@ -26,9 +35,24 @@ pub struct TrayIcon {
     tray_target: Option<Retained<TrayTarget>>,
     id: TrayIconId,
     attrs: TrayIconAttributes,
+    attributed_title: Option<AttributedTitle>,
+    status_item_lost_handler: Option<StatusItemLostHandler>,
     mtm: MainThreadMarker,
 }
 
+/// How often, in seconds, to check whether the status item was removed by the system.
+const STATUS_ITEM_CHECK_INTERVAL: f64 = 1.0;
+
+/// Called by the status item check when the status item was removed by the system.
+#[derive(Clone)]
+struct StatusItemLostHandler(Rc<dyn Fn()>);
+
+impl fmt::Debug for StatusItemLostHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StatusItemLostHandler")
+    }
+}
+
 impl TrayIcon {
     pub fn new(id: TrayIconId, attrs: TrayIconAttributes) -> crate::Result<Self> {
         let mtm = MainThreadMarker::new().ok_or(Error::NotMainThread)?;
@@ -39,6 +63,8 @@ impl TrayIcon {
             tray_target: Some(tray_target),
             id,
             attrs,
+            attributed_title: None,
+            status_item_lost_handler: None,
             mtm,
         };
 
@@ -54,22 +80,33 @@ impl TrayIcon {
             NSStatusBar::systemStatusBar().statusItemWithLength(NSVariableStatusItemLength)
         };
 
+        if let Some(autosave_name) = &attrs.autosave_name {
+            unsafe { ns_status_item.setAutosaveName(Some(&NSString::from_str(autosave_name))) };
+        }
+        // Always set the visibility, the system restores it for a status item with an autosave name,
+        // which would keep it hidden if it was hidden when the application last quit.
+        unsafe { ns_status_item.setVisible(attrs.visible) };
+
         set_icon_for_ns_status_item_button(
             &ns_status_item,
             attrs.icon.clone(),
+            attrs.dark_mode_icon.as_ref(),
             attrs.icon_is_template,
+            attrs.icon_size_hint,
             mtm,
         )?;
 
-        if let Some(menu) = &attrs.menu {
-            unsafe {
-                ns_status_item.setMenu((menu.ns_menu() as *const NSMenu).as_ref());
-            }
-        }
+        let ns_menu = attrs
+            .menu
+            .as_deref()
+            .and_then(|menu| unsafe { Retained::retain(menu.ns_menu().cast::<NSMenu>()) });
+        attach_menu(&ns_status_item, ns_menu.as_deref());
 
         Self::set_tooltip_inner(&ns_status_item, attrs.tooltip.clone(), mtm)?;
         Self::set_title_inner(&ns_status_item, attrs.title.clone(), mtm);
 
+        apply_icon_padding(&ns_status_item, attrs.icon_padding, mtm);
+
         let tray_target = unsafe {
             let button = ns_status_item.button(mtm).unwrap();
 
@@ -77,14 +114,19 @@ impl TrayIcon {
 
             let target = mtm.alloc().set_ivars(TrayTargetIvars {
                 id: NSString::from_str(&id.0),
-                menu: RefCell::new(
-                    attrs
-                        .menu
-                        .as_deref()
-                        .and_then(|menu| Retained::retain(menu.ns_menu().cast::<NSMenu>())),
-                ),
+                menu: RefCell::new(ns_menu),
                 status_item: ns_status_item.retain(),
                 menu_on_left_click: Cell::new(attrs.menu_on_left_click),
+                menu_on_right_click: Cell::new(attrs.menu_on_right_click),
+                suppress_click_event_on_menu_open: attrs.suppress_click_event_on_menu_open,
+                icon_padding: attrs.icon_padding,
+                hidden_in_overflow: Cell::new(false),
+                icon: RefCell::new(attrs.icon.clone()),
+                dark_mode_icon: attrs.dark_mode_icon.clone(),
+                icon_is_template: Cell::new(attrs.icon_is_template),
+                icon_size_hint: attrs.icon_size_hint,
+                status_item_lost_handler: RefCell::new(None),
+                status_item_check: RefCell::new(None),
             });
             let tray_target: Retained<TrayTarget> =
                 msg_send_id![super(target), initWithFrame: frame];
@@ -92,16 +134,118 @@ impl TrayIcon {
 
             button.addSubview(&tray_target);
 
+            if let Some(window) = button.window() {
+                let mut behavior = window.collectionBehavior();
+                behavior.set(
+                    NSWindowCollectionBehavior::CanJoinAllSpaces,
+                    attrs.visible_on_all_spaces,
+                );
+                window.setCollectionBehavior(behavior);
+
+                // The status item's window is occluded when the menu bar runs out of space for it.
+                let window: &AnyObject = &window;
+                NSNotificationCenter::defaultCenter().addObserver_selector_name_object(
+                    &tray_target,
+                    sel!(windowDidChangeOcclusionState:),
+                    Some(NSWindowDidChangeOcclusionStateNotification),
+                    Some(window),
+                );
+            }
+
             tray_target
         };
 
         Ok((ns_status_item, tray_target))
     }
 
+    /// Creates the status item from the current attributes if it doesn't exist.
+    fn ensure_created(&mut self) -> crate::Result<()> {
+        if self.ns_status_item.is_none() {
+            let (ns_status_item, tray_target) = Self::create(&self.id, &self.attrs, self.mtm)?;
+            if self.attributed_title.is_some() {
+                Self::set_attributed_title_inner(
+                    &ns_status_item,
+                    self.attributed_title.as_ref(),
+                    self.mtm,
+                );
+                tray_target.update_dimensions();
+            }
+            self.ns_status_item = Some(ns_status_item);
+            self.tray_target = Some(tray_target);
+            self.start_status_item_check();
+        }
+        Ok(())
+    }
+
+    /// Sets the function called when the status item was removed from the status bar by the system,
+    /// which should call [`TrayIcon::recreate_if_lost`], and starts checking for it periodically.
+    ///
+    /// The tray icon can't re-create itself from the check as it doesn't own its shared state.
+    pub(crate) fn set_status_item_lost_handler(&mut self, handler: impl Fn() + 'static) {
+        self.status_item_lost_handler = Some(StatusItemLostHandler(Rc::new(handler)));
+        self.start_status_item_check();
+    }
+
+    /// Starts a timer owned by the tray target which calls the status item lost handler,
+    /// so a lost status item is re-created without waiting for the tray icon to be updated.
+    fn start_status_item_check(&self) {
+        let (Some(tray_target), Some(handler)) =
+            (&self.tray_target, &self.status_item_lost_handler)
+        else {
+            return;
+        };
+
+        *tray_target.ivars().status_item_lost_handler.borrow_mut() = Some(handler.clone());
+        let timer = unsafe {
+            NSTimer::scheduledTimerWithTimeInterval_target_selector_userInfo_repeats(
+                STATUS_ITEM_CHECK_INTERVAL,
+                tray_target,
+                sel!(checkStatusItem:),
+                None,
+                true,
+            )
+        };
+        if let Some(previous) = tray_target.ivars().status_item_check.replace(Some(timer)) {
+            unsafe { previous.invalidate() };
+        }
+    }
+
+    /// Re-creates the status item if it was removed from the status bar by the system,
+    /// for example when the user resets or customizes the menu bar.
+    pub(crate) fn recreate_if_lost(&mut self) -> crate::Result<()> {
+        let lost = self
+            .ns_status_item
+            .as_ref()
+            .is_some_and(|item| unsafe { item.button(self.mtm) }.is_none());
+        if lost {
+            self.remove();
+            self.ensure_created()?;
+            TrayIconEvent::send(TrayIconEvent::Recreated {
+                id: self.id.clone(),
+            });
+        }
+        Ok(())
+    }
+
     fn remove(&mut self) {
         if let (Some(ns_status_item), Some(tray_target)) = (&self.ns_status_item, &self.tray_target)
         {
+            if let Some(menu) = tray_target.ivars().menu.borrow_mut().take() {
+                detach_menu(&menu);
+            }
+
+            // the timer retains the tray target until it is invalidated
+            if let Some(timer) = tray_target.ivars().status_item_check.borrow_mut().take() {
+                unsafe { timer.invalidate() };
+            }
+            tray_target
+                .ivars()
+                .status_item_lost_handler
+                .borrow_mut()
+                .take();
+
             unsafe {
+                NSNotificationCenter::defaultCenter().removeObserver(tray_target);
                 NSStatusBar::systemStatusBar().removeStatusItem(ns_status_item);
                 tray_target.removeFromSuperview();
             }
@@ -111,36 +255,66 @@ impl TrayIcon {
         self.tray_target = None;
     }
 
-    pub fn set_icon(&mut self, icon: Option<Icon>) -> crate::Result<()> {
+    pub fn set_icon(&mut self, icon: Option<Icon>) -> crate::Result<Option<Icon>> {
+        self.set_icon_and_template(icon, self.attrs.icon_is_template)
+    }
+
+    /// Returns the previous icon.
+    pub fn set_icon_and_template(
+        &mut self,
+        icon: Option<Icon>,
+        is_template: bool,
+    ) -> crate::Result<Option<Icon>> {
+        self.recreate_if_lost()?;
         if let (Some(ns_status_item), Some(tray_target)) = (&self.ns_status_item, &self.tray_target)
         {
-            set_icon_for_ns_status_item_button(ns_status_item, icon.clone(), false, self.mtm)?;
+            set_icon_for_ns_status_item_button(
+                ns_status_item,
+                icon.clone(),
+                self.attrs.dark_mode_icon.as_ref(),
+                is_template,
+                self.attrs.icon_size_hint,
+                self.mtm,
+            )?;
             tray_target.update_dimensions();
+            *tray_target.ivars().icon.borrow_mut() = icon.clone();
+            tray_target.ivars().icon_is_template.set(is_template);
         }
-        self.attrs.icon = icon;
-        Ok(())
+        self.attrs.icon_is_template = is_template;
+        Ok(std::mem::replace(&mut self.attrs.icon, icon))
     }
 
-    pub fn set_menu(&mut self, menu: Option<Box<dyn menu::ContextMenu>>) {
+    pub fn set_menu(&mut self, menu: Option<Box<dyn menu::ContextMenu>>) -> crate::Result<()> {
+        self.recreate_if_lost()?;
         if let (Some(ns_status_item), Some(tray_target)) = (&self.ns_status_item, &self.tray_target)
         {
-            unsafe {
-                let menu = menu
-                    .as_ref()
-                    .and_then(|m| m.ns_menu().cast::<NSMenu>().as_ref())
-                    .map(|menu| menu.retain());
-                ns_status_item.setMenu(menu.as_deref());
-                if let Some(menu) = &menu {
-                    let () = msg_send![menu, setDelegate: &**ns_status_item];
-                }
+            let ns_menu = menu
+                .as_ref()
+                .map(|m| unsafe { Retained::retain(m.ns_menu().cast::<NSMenu>()) });
+            if matches!(ns_menu, Some(None)) {
+                return Err(Error::OsError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "the menu has no underlying NSMenu",
+                )));
+            }
+            let ns_menu = ns_menu.flatten();
 
-                *tray_target.ivars().menu.borrow_mut() = menu;
+            // detach the previous menu, including when the menu is cleared with `None`,
+            // so it doesn't keep a delegate pointing to this status item
+            if let Some(previous_menu) = tray_target.ivars().menu.borrow_mut().take() {
+                detach_menu(&previous_menu);
             }
+
+            attach_menu(ns_status_item, ns_menu.as_deref());
+
+            *tray_target.ivars().menu.borrow_mut() = ns_menu;
         }
         self.attrs.menu = menu;
+        Ok(())
     }
 
     pub fn set_tooltip<S: AsRef<str>>(&mut self, tooltip: Option<S>) -> crate::Result<()> {
+        self.recreate_if_lost()?;
         let tooltip = tooltip.map(|s| s.as_ref().to_string());
         if let (Some(ns_status_item), Some(tray_target)) = (&self.ns_status_item, &self.tray_target)
         {
@@ -165,7 +339,8 @@ impl TrayIcon {
         Ok(())
     }
 
-    pub fn set_title<S: AsRef<str>>(&mut self, title: Option<S>) {
+    pub fn set_title<S: AsRef<str>>(&mut self, title: Option<S>) -> crate::Result<()> {
+        self.recreate_if_lost()?;
         let title = title.map(|s| s.as_ref().to_string());
         if let (Some(ns_status_item), Some(tray_target)) = (&self.ns_status_item, &self.tray_target)
         {
@@ -173,6 +348,7 @@ impl TrayIcon {
             tray_target.update_dimensions();
         }
         self.attrs.title = title;
+        Ok(())
     }
 
     fn set_title_inner<S: AsRef<str>>(
@@ -180,29 +356,89 @@ impl TrayIcon {
         title: Option<S>,
         mtm: MainThreadMarker,
     ) {
-        if let Some(title) = title {
-            unsafe {
-                if let Some(button) = ns_status_item.button(mtm) {
-                    button.setTitle(&NSString::from_str(title.as_ref()));
-                }
+        unsafe {
+            if let Some(button) = ns_status_item.button(mtm) {
+                button.setTitle(&ns_title(title.as_ref().map(|t| t.as_ref())));
+                update_image_position(&button);
             }
         }
     }
 
-    pub fn set_visible(&mut self, visible: bool) -> crate::Result<()> {
-        if visible {
-            if self.ns_status_item.is_none() {
-                let (ns_status_item, tray_target) = Self::create(&self.id, &self.attrs, self.mtm)?;
-                self.ns_status_item = Some(ns_status_item);
-                self.tray_target = Some(tray_target);
+    pub fn set_attributed_title(&mut self, title: Option<AttributedTitle>) -> crate::Result<()> {
+        self.recreate_if_lost()?;
+        if let (Some(ns_status_item), Some(tray_target)) = (&self.ns_status_item, &self.tray_target)
+        {
+            Self::set_attributed_title_inner(ns_status_item, title.as_ref(), self.mtm);
+            tray_target.update_dimensions();
+        }
+        self.attributed_title = title;
+        Ok(())
+    }
+
+    fn set_attributed_title_inner(
+        ns_status_item: &NSStatusItem,
+        title: Option<&AttributedTitle>,
+        mtm: MainThreadMarker,
+    ) {
+        unsafe {
+            let Some(button) = ns_status_item.button(mtm) else {
+                return;
+            };
+
+            let Some(title) = title else {
+                button.setTitle(&NSString::from_str(""));
+                update_image_position(&button);
+                return;
+            };
+
+            let mut keys: Vec<&NSAttributedStringKey> = Vec::new();
+            let mut objects: Vec<Retained<AnyObject>> = Vec::new();
+            if let Some((r, g, b, a)) = title.color {
+                keys.push(NSForegroundColorAttributeName);
+                objects.push(Retained::cast(NSColor::colorWithSRGBRed_green_blue_alpha(
+                    r, g, b, a,
+                )));
             }
-        } else {
-            self.remove();
+            if let Some(font_size) = title.font_size {
+                keys.push(NSFontAttributeName);
+                objects.push(Retained::cast(NSFont::menuBarFontOfSize(font_size)));
+            }
+
+            let attributes = NSDictionary::from_vec(&keys, objects);
+            let attributed_title = NSAttributedString::new_with_attributes(
+                &NSString::from_str(&title.text),
+                &attributes,
+            );
+            button.setAttributedTitle(&attributed_title);
+            update_image_position(&button);
         }
+    }
+
+    pub fn set_visible(&mut self, visible: bool) -> crate::Result<()> {
+        self.recreate_if_lost()?;
+        self.ensure_created()?;
+        // Hiding the status item instead of removing it keeps its position in the menu bar
+        // when it is shown again, removing it would place it as if it was newly added.
+        if let Some(ns_status_item) = &self.ns_status_item {
+            unsafe { ns_status_item.setVisible(visible) };
+        }
+        self.attrs.visible = visible;
 
         Ok(())
     }
 
+    pub fn is_visible(&self) -> bool {
+        self.attrs.visible
+    }
+
+    pub fn has_icon(&self) -> bool {
+        self.attrs.icon.is_some()
+    }
+
+    pub fn has_menu(&self) -> bool {
+        self.attrs.menu.is_some()
+    }
+
     pub fn set_icon_as_template(&mut self, is_template: bool) {
         if let Some(ns_status_item) = &self.ns_status_item {
             unsafe {
@@ -223,64 +459,199 @@ impl TrayIcon {
         self.attrs.menu_on_left_click = enable;
     }
 
-    pub fn rect(&self) -> Option<Rect> {
-        let ns_status_item = self.ns_status_item.as_deref()?;
+    pub fn set_show_menu_on_right_click(&mut self, enable: bool) {
+        if let Some(tray_target) = &self.tray_target {
+            tray_target.ivars().menu_on_right_click.set(enable);
+        }
+        self.attrs.menu_on_right_click = enable;
+    }
+
+    pub fn rect(&self) -> crate::Result<Option<Rect>> {
+        let Some(ns_status_item) = self
+            .ns_status_item
+            .as_deref()
+            .filter(|item| unsafe { item.isVisible() })
+        else {
+            return Ok(None);
+        };
         unsafe {
             let button = ns_status_item.button(self.mtm).unwrap();
             let window = button.window();
-            window.map(|window| get_tray_rect(&window))
+            Ok(window.map(|window| get_tray_rect(&window)))
         }
     }
 }
 
 impl Drop for TrayIcon {
     fn drop(&mut self) {
-        self.remove()
+        self.remove();
+        super::send_destroyed(&self.id);
     }
 }
 
+#[cfg(feature = "arc")]
+impl TrayIcon {
+    pub(crate) fn owner_thread(&self) -> OwnerThread {}
+}
+
+/// Tray icons on macOS are always owned by the main thread.
+#[cfg(feature = "arc")]
+pub(crate) type OwnerThread = ();
+
+#[cfg(feature = "arc")]
+pub(crate) fn is_on_owner_thread(_owner: OwnerThread) -> bool {
+    MainThreadMarker::new().is_some()
+}
+
+/// Runs `f` on the main thread and blocks until it returns,
+/// which requires the main thread to be running its event loop.
+#[cfg(feature = "arc")]
+pub(crate) fn run_on_owner_thread<R: Send + 'static>(
+    _owner: OwnerThread,
+    f: impl FnOnce() -> R + Send + 'static,
+) -> crate::Result<R> {
+    Ok(objc2_foundation::run_on_main(move |_| f()))
+}
+
+pub fn init() -> crate::Result<()> {
+    MainThreadMarker::new().ok_or(Error::NotMainThread)?;
+    Ok(())
+}
+
+/// Sets the image of the status item button, using `dark_mode_icon` instead of `icon`
+/// if provided and the button has a dark appearance.
 fn set_icon_for_ns_status_item_button(
     ns_status_item: &NSStatusItem,
     icon: Option<Icon>,
+    dark_mode_icon: Option<&Icon>,
     icon_is_template: bool,
+    size_hint: Option<(u32, u32)>,
     mtm: MainThreadMarker,
 ) -> crate::Result<()> {
     let button = unsafe { ns_status_item.button(mtm).unwrap() };
 
-    if let Some(icon) = icon {
-        let png_icon = icon.inner.to_png()?;
-
-        let (width, height) = icon.inner.get_size();
+    let icon = match dark_mode_icon {
+        Some(dark_mode_icon) if is_dark_appearance(&button) => Some(dark_mode_icon.clone()),
+        _ => icon,
+    };
 
-        let icon_height: f64 = 18.0;
-        let icon_width: f64 = (width as f64) / (height as f64 / icon_height);
+    if let Some(icon) = icon {
+        let (width, height) = icon.inner.get_size(mtm);
+
+        let (icon_width, icon_height) = match size_hint {
+            Some((width, height)) => (width as f64, height as f64),
+            None => {
+                let icon_height: f64 = 18.0;
+                // an empty image has no aspect ratio to keep
+                let icon_width: f64 = if height == 0 {
+                    icon_height
+                } else {
+                    (width as f64) / (height as f64 / icon_height)
+                };
+                (icon_width, icon_height)
+            }
+        };
 
+        let nsimage = icon.inner.to_ns_image(mtm)?;
         unsafe {
             // build our icon
-            let nsdata = NSData::from_vec(png_icon);
-
-            let nsimage = NSImage::initWithData(NSImage::alloc(), &nsdata).unwrap();
             let new_size = NSSize::new(icon_width, icon_height);
 
             button.setImage(Some(&nsimage));
             nsimage.setSize(new_size);
-            // The image is to the right of the title
-            button.setImagePosition(NSCellImagePosition::NSImageLeft);
             nsimage.setTemplate(icon_is_template);
         }
     } else {
         unsafe { button.setImage(None) };
     }
 
+    update_image_position(&button);
+
     Ok(())
 }
 
+/// Returns whether `view` is drawn with a dark appearance.
+fn is_dark_appearance(view: &NSView) -> bool {
+    let appearance: Retained<NSAppearance> = unsafe { msg_send_id![view, effectiveAppearance] };
+    let names = NSArray::from_slice(&[unsafe { NSAppearanceNameAqua }, unsafe {
+        NSAppearanceNameDarkAqua
+    }]);
+    appearance
+        .bestMatchFromAppearancesWithNames(&names)
+        .is_some_and(|name| &*name == unsafe { NSAppearanceNameDarkAqua })
+}
+
+/// Sizes the status item to fit its content plus `padding` on the left and right,
+/// the status item keeps fitting its content on its own when there is no padding.
+fn apply_icon_padding(ns_status_item: &NSStatusItem, padding: Option<u8>, mtm: MainThreadMarker) {
+    let Some(padding) = padding else {
+        return;
+    };
+
+    unsafe {
+        if let Some(button) = ns_status_item.button(mtm) {
+            ns_status_item.setLength(button.fittingSize().width + 2.0 * padding as f64);
+        }
+    }
+}
+
+/// Shows `menu` when the status item is clicked, and makes the status item its delegate.
+fn attach_menu(ns_status_item: &NSStatusItem, menu: Option<&NSMenu>) {
+    unsafe {
+        ns_status_item.setMenu(menu);
+        if let Some(menu) = menu {
+            let () = msg_send![menu, setDelegate: ns_status_item];
+        }
+    }
+}
+
+/// Clears the delegate set by [`attach_menu`], so `menu` doesn't point
+/// to a status item that was removed or uses another menu.
+fn detach_menu(menu: &NSMenu) {
+    unsafe {
+        let () = msg_send![menu, setDelegate: std::ptr::null::<AnyObject>()];
+    }
+}
+
+/// Positions the image depending on whether the button has an icon, a title or both,
+/// so no empty space is left for a missing icon or title.
+/// The title of the status item button, where an empty title clears it.
+fn ns_title(title: Option<&str>) -> Retained<NSString> {
+    NSString::from_str(title.unwrap_or(""))
+}
+
+fn update_image_position(button: &NSButton) {
+    unsafe {
+        let has_image = button.image().is_some();
+        let has_title = button.title().length() > 0;
+        let position = match (has_image, has_title) {
+            // The image is to the left of the title
+            (true, true) => NSCellImagePosition::NSImageLeft,
+            (true, false) => NSCellImagePosition::NSImageOnly,
+            (false, _) => NSCellImagePosition::NSNoImage,
+        };
+        button.setImagePosition(position);
+    }
+}
+
 #[derive(Debug)]
 struct TrayTargetIvars {
     id: Retained<NSString>,
     menu: RefCell<Option<Retained<NSMenu>>>,
     status_item: Retained<NSStatusItem>,
     menu_on_left_click: Cell<bool>,
+    menu_on_right_click: Cell<bool>,
+    suppress_click_event_on_menu_open: bool,
+    icon_padding: Option<u8>,
+    hidden_in_overflow: Cell<bool>,
+    /// The icon and its settings, to switch to or from `dark_mode_icon`
+    /// when the appearance changes.
+    icon: RefCell<Option<Icon>>,
+    dark_mode_icon: Option<Icon>,
+    icon_is_template: Cell<bool>,
+    icon_size_hint: Option<(u32, u32)>,
+    status_item_lost_handler: RefCell<Option<StatusItemLostHandler>>,
+    status_item_check: RefCell<Option<Retained<NSTimer>>>,
 }
 
 declare_class!(
@@ -289,7 +660,10 @@ declare_class!(
     unsafe impl ClassType for TrayTarget {
         type Super = NSView;
         type Mutability = mutability::MainThreadOnly;
-        const NAME: &'static str = "TaoTrayTarget";
+        // Objective-C class names are global to the process, so this needs to be unique
+        // to this crate to not conflict with `tao`, which is commonly used alongside it
+        // and previously declared a class with the same name.
+        const NAME: &'static str = "TrayIconCrateTrayTarget";
     }
 
     impl DeclaredClass for TrayTarget {
@@ -300,15 +674,17 @@ declare_class!(
     unsafe impl TrayTarget {
         #[method(mouseDown:)]
         fn on_mouse_down(&self, event: &NSEvent) {
-            send_mouse_event(
-                self,
-                event,
-                MouseEventType::Click,
-                Some(MouseClickEvent {
-                    button: MouseButton::Left,
-                    state: MouseButtonState::Down,
-                }),
-            );
+            if !suppress_click_event(self, MouseButton::Left) {
+                send_mouse_event(
+                    self,
+                    event,
+                    MouseEventType::Click,
+                    Some(MouseClickEvent {
+                        button: MouseButton::Left,
+                        state: MouseButtonState::Down,
+                    }),
+                );
+            }
             on_tray_click(self, MouseButton::Left);
         }
 
@@ -332,20 +708,27 @@ declare_class!(
 
         #[method(rightMouseDown:)]
         fn on_right_mouse_down(&self, event: &NSEvent) {
-            send_mouse_event(
-                self,
-                event,
-                MouseEventType::Click,
-                Some(MouseClickEvent {
-                    button: MouseButton::Right,
-                    state: MouseButtonState::Down,
-                }),
-            );
+            if !suppress_click_event(self, MouseButton::Right) {
+                send_mouse_event(
+                    self,
+                    event,
+                    MouseEventType::Click,
+                    Some(MouseClickEvent {
+                        button: MouseButton::Right,
+                        state: MouseButtonState::Down,
+                    }),
+                );
+            }
             on_tray_click(self, MouseButton::Right);
         }
 
         #[method(rightMouseUp:)]
         fn on_right_mouse_up(&self, event: &NSEvent) {
+            let mtm = MainThreadMarker::from(self);
+            unsafe {
+                let button = self.ivars().status_item.button(mtm).unwrap();
+                button.highlight(false);
+            }
             send_mouse_event(
                 self,
                 event,
@@ -401,10 +784,109 @@ declare_class!(
 
         #[method(mouseMoved:)]
         fn on_mouse_moved(&self, event: &NSEvent) {
+            // The user is interacting with the menu, not the icon.
+            if is_menu_open(self) {
+                return;
+            }
             send_mouse_event(self, event, MouseEventType::Move, None);
         }
     }
 
+    // Keyboard focus changes, e.g. when navigating the status bar with VoiceOver
+    unsafe impl TrayTarget {
+        #[method(acceptsFirstResponder)]
+        fn accepts_first_responder(&self) -> bool {
+            true
+        }
+
+        #[method(becomeFirstResponder)]
+        fn become_first_responder(&self) -> bool {
+            let became: bool = unsafe { msg_send![super(self), becomeFirstResponder] };
+            if became {
+                send_focus_event(self, MouseEventType::Enter);
+            }
+            became
+        }
+
+        #[method(resignFirstResponder)]
+        fn resign_first_responder(&self) -> bool {
+            let resigned: bool = unsafe { msg_send![super(self), resignFirstResponder] };
+            if resigned {
+                send_focus_event(self, MouseEventType::Leave);
+            }
+            resigned
+        }
+    }
+
+    // Switching between light and dark mode
+    unsafe impl TrayTarget {
+        #[method(viewDidChangeEffectiveAppearance)]
+        fn view_did_change_effective_appearance(&self) {
+            let _: () = unsafe { msg_send![super(self), viewDidChangeEffectiveAppearance] };
+
+            let ivars = self.ivars();
+            if ivars.dark_mode_icon.is_some() {
+                let _ = set_icon_for_ns_status_item_button(
+                    &ivars.status_item,
+                    ivars.icon.borrow().clone(),
+                    ivars.dark_mode_icon.as_ref(),
+                    ivars.icon_is_template.get(),
+                    ivars.icon_size_hint,
+                    MainThreadMarker::from(self),
+                );
+                self.update_dimensions();
+            }
+        }
+    }
+
+    // Notifications from the status item's window
+    unsafe impl TrayTarget {
+        #[method(windowDidChangeOcclusionState:)]
+        fn window_did_change_occlusion_state(&self, _notification: &NSNotification) {
+            let mtm = MainThreadMarker::from(self);
+            let Some(window) = unsafe { self.ivars().status_item.button(mtm) }
+                .and_then(|button| button.window())
+            else {
+                return;
+            };
+
+            let visible = window
+                .occlusionState()
+                .contains(NSWindowOcclusionState::Visible);
+            // The window is also occluded when the whole menu bar is hidden,
+            // for example by a full screen app, which isn't an overflow.
+            let hidden = !visible && unsafe { NSMenu::menuBarVisible(mtm) };
+            if hidden == self.ivars().hidden_in_overflow.replace(hidden) {
+                return;
+            }
+
+            let id = TrayIconId(self.ivars().id.to_string().into());
+            TrayIconEvent::send(if hidden {
+                TrayIconEvent::OverflowHidden { id }
+            } else {
+                TrayIconEvent::OverflowRestored { id }
+            });
+        }
+    }
+
+    // Checking whether the status item was removed by the system
+    unsafe impl TrayTarget {
+        #[method(checkStatusItem:)]
+        fn check_status_item(&self, _timer: &NSTimer) {
+            let mtm = MainThreadMarker::from(self);
+            if unsafe { self.ivars().status_item.button(mtm) }.is_some() {
+                return;
+            }
+
+            // re-creating the status item releases this target, keep it alive until the handler returns
+            let _this = self.retain();
+            let handler = self.ivars().status_item_lost_handler.borrow().clone();
+            if let Some(StatusItemLostHandler(handler)) = handler {
+                handler();
+            }
+        }
+    }
+
     // Tracking mouse enter/exit/move events
     unsafe impl TrayTarget {
         #[method(updateTrackingAreas)]
@@ -444,6 +926,7 @@ declare_class!(
 impl TrayTarget {
     fn update_dimensions(&self) {
         let mtm = MainThreadMarker::from(self);
+        apply_icon_padding(&self.ivars().status_item, self.ivars().icon_padding, mtm);
         unsafe {
             let button = self.ivars().status_item.button(mtm).unwrap();
             self.setFrame(button.frame());
@@ -451,23 +934,53 @@ impl TrayTarget {
     }
 }
 
+/// Returns whether clicking `button` opens the tray menu.
+fn will_show_menu(this: &TrayTarget, button: MouseButton) -> bool {
+    let show_menu = match button {
+        MouseButton::Left => this.ivars().menu_on_left_click.get(),
+        MouseButton::Right => this.ivars().menu_on_right_click.get(),
+        _ => false,
+    };
+    show_menu
+        && this
+            .ivars()
+            .menu
+            .borrow()
+            .as_ref()
+            .is_some_and(|menu| unsafe { menu.numberOfItems() } > 0)
+}
+
+/// Returns whether the tray menu is currently shown.
+fn is_menu_open(this: &TrayTarget) -> bool {
+    let mtm = MainThreadMarker::from(this);
+    let Some(menu) = this.ivars().menu.borrow().clone() else {
+        return false;
+    };
+    unsafe {
+        // The status item keeps its button highlighted while the menu is shown,
+        // even before any item is hovered.
+        menu.highlightedItem().is_some()
+            || this
+                .ivars()
+                .status_item
+                .button(mtm)
+                .is_some_and(|button| button.isHighlighted())
+    }
+}
+
+/// Returns whether the click event for `button` should not be emitted
+/// because it opens the tray menu.
+fn suppress_click_event(this: &TrayTarget, button: MouseButton) -> bool {
+    this.ivars().suppress_click_event_on_menu_open && will_show_menu(this, button)
+}
+
 fn on_tray_click(this: &TrayTarget, button: MouseButton) {
     let mtm = MainThreadMarker::from(this);
     unsafe {
         let ns_button = this.ivars().status_item.button(mtm).unwrap();
 
-        let menu_on_left_click = this.ivars().menu_on_left_click.get();
-        if button == MouseButton::Right || (menu_on_left_click && button == MouseButton::Left) {
-            let has_items = if let Some(menu) = &*this.ivars().menu.borrow() {
-                menu.numberOfItems() > 0
-            } else {
-                false
-            };
-            if has_items {
-                ns_button.performClick(None);
-            } else {
-                ns_button.highlight(true);
-            }
+        if will_show_menu(this, button) {
+            ns_button.performClick(None);
         } else {
             ns_button.highlight(true);
         }
@@ -497,10 +1010,18 @@ fn send_mouse_event(
 ) {
     let mtm = MainThreadMarker::from(this);
     unsafe {
-        let tray_id = TrayIconId(this.ivars().id.to_string());
-
-        // icon position & size
-        let window = event.window(mtm).unwrap();
+        let tray_id = TrayIconId(this.ivars().id.to_string().into());
+
+        // icon position & size, the event may have no window, for example when
+        // it is delivered while the system goes to sleep
+        let Some(window) = event.window(mtm).or_else(|| {
+            this.ivars()
+                .status_item
+                .button(mtm)
+                .and_then(|button| button.window())
+        }) else {
+            return;
+        };
         let icon_rect = get_tray_rect(&window);
 
         // cursor position
@@ -512,6 +1033,18 @@ fn send_mouse_event(
         )
         .to_physical(scale_factor);
 
+        // a double click is reported in addition to the clicks it consists of
+        let double_click = click_event
+            .as_ref()
+            .filter(|click| click.state == MouseButtonState::Down && event.clickCount() == 2)
+            .map(|click| TrayIconEvent::DoubleClick {
+                id: tray_id.clone(),
+                position: cursor_position,
+                rect: icon_rect,
+                scale_factor,
+                button: click.button,
+            });
+
         let event = match mouse_event_type {
             MouseEventType::Click => {
                 let click_event = click_event.unwrap();
@@ -519,6 +1052,7 @@ fn send_mouse_event(
                     id: tray_id,
                     position: cursor_position,
                     rect: icon_rect,
+                    scale_factor,
                     button: click_event.button,
                     button_state: click_event.state,
                 }
@@ -527,23 +1061,64 @@ fn send_mouse_event(
                 id: tray_id,
                 position: cursor_position,
                 rect: icon_rect,
+                scale_factor,
             },
             MouseEventType::Leave => TrayIconEvent::Leave {
                 id: tray_id,
                 position: cursor_position,
                 rect: icon_rect,
+                scale_factor,
             },
             MouseEventType::Move => TrayIconEvent::Move {
                 id: tray_id,
                 position: cursor_position,
                 rect: icon_rect,
+                scale_factor,
             },
         };
 
         TrayIconEvent::send(event);
+        if let Some(double_click) = double_click {
+            TrayIconEvent::send(double_click);
+        }
     }
 }
 
+/// Sends an enter or leave event for a keyboard focus change,
+/// positioned at the center of the tray icon.
+fn send_focus_event(this: &TrayTarget, mouse_event_type: MouseEventType) {
+    let Some(window) = this.window() else {
+        return;
+    };
+
+    let id = TrayIconId(this.ivars().id.to_string().into());
+    let rect = get_tray_rect(&window);
+    let position = crate::dpi::PhysicalPosition::new(
+        rect.position.x + rect.size.width as f64 / 2.0,
+        rect.position.y + rect.size.height as f64 / 2.0,
+    );
+
+    let scale_factor = window.backingScaleFactor();
+
+    let event = match mouse_event_type {
+        MouseEventType::Enter => TrayIconEvent::Enter {
+            id,
+            position,
+            rect,
+            scale_factor,
+        },
+        MouseEventType::Leave => TrayIconEvent::Leave {
+            id,
+            position,
+            rect,
+            scale_factor,
+        },
+        _ => return,
+    };
+
+    TrayIconEvent::send(event);
+}
+
 #[derive(Debug)]
 enum MouseEventType {
     Click,
@@ -569,6 +1144,23 @@ struct MouseClickEvent {
 ///
 /// This conversion happens to be symmetric, so we only need this one function
 /// to convert between the two coordinate systems.
+///
+/// Both coordinate systems are in points, so this uses the height of the main display
+/// in points rather than in pixels, the conversion to physical pixels happens afterwards
+/// using the scale factor of the display the tray icon is on.
 fn flip_window_screen_coordinates(y: f64) -> f64 {
-    CGDisplay::main().pixels_high() as f64 - y
+    CGDisplay::main().bounds().size.height - y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ns_title;
+
+    // Status items can only be created on the main thread, which tests don't run on,
+    // so this only covers the title given to the status item button.
+    #[test]
+    fn none_title_clears_title() {
+        assert_eq!(ns_title(Some("title")).to_string(), "title");
+        assert_eq!(ns_title(None).to_string(), "");
+    }
 }
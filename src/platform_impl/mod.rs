@@ -13,3 +13,157 @@ mod platform;
 mod platform;
 
 pub(crate) use self::platform::*;
+
+/// Sends [`TrayIconEvent::Destroyed`](crate::TrayIconEvent::Destroyed), called when dropping
+/// a platform tray icon, which happens exactly once however many clones the tray icon has.
+fn send_destroyed(id: &crate::TrayIconId) {
+    #[cfg(feature = "log")]
+    log::debug!("removed tray icon with id `{}`", id.0);
+
+    crate::TrayIconEvent::send(crate::TrayIconEvent::Destroyed { id: id.clone() });
+}
+
+/// The methods every platform's [`TrayIcon`] must expose with identical signatures.
+///
+/// It is only implemented in tests, by forwarding to the inherent methods,
+/// so a platform whose signatures drift from the others fails to compile.
+#[cfg(test)]
+#[allow(dead_code)]
+trait PlatformTrayIconInterface: Sized {
+    fn new(id: crate::TrayIconId, attrs: crate::TrayIconAttributes) -> crate::Result<Self>;
+    fn set_icon(&mut self, icon: Option<crate::Icon>) -> crate::Result<Option<crate::Icon>>;
+    fn set_menu(&mut self, menu: Option<Box<dyn crate::menu::ContextMenu>>) -> crate::Result<()>;
+    fn set_tooltip<S: AsRef<str>>(&mut self, tooltip: Option<S>) -> crate::Result<()>;
+    fn set_title<S: AsRef<str>>(&mut self, title: Option<S>) -> crate::Result<()>;
+    fn set_visible(&mut self, visible: bool) -> crate::Result<()>;
+    fn is_visible(&self) -> bool;
+    fn has_icon(&self) -> bool;
+    fn has_menu(&self) -> bool;
+    fn rect(&self) -> crate::Result<Option<crate::Rect>>;
+}
+
+#[cfg(test)]
+impl PlatformTrayIconInterface for TrayIcon {
+    fn new(id: crate::TrayIconId, attrs: crate::TrayIconAttributes) -> crate::Result<Self> {
+        TrayIcon::new(id, attrs)
+    }
+
+    fn set_icon(&mut self, icon: Option<crate::Icon>) -> crate::Result<Option<crate::Icon>> {
+        TrayIcon::set_icon(self, icon)
+    }
+
+    fn set_menu(&mut self, menu: Option<Box<dyn crate::menu::ContextMenu>>) -> crate::Result<()> {
+        TrayIcon::set_menu(self, menu)
+    }
+
+    fn set_tooltip<S: AsRef<str>>(&mut self, tooltip: Option<S>) -> crate::Result<()> {
+        TrayIcon::set_tooltip(self, tooltip)
+    }
+
+    fn set_title<S: AsRef<str>>(&mut self, title: Option<S>) -> crate::Result<()> {
+        TrayIcon::set_title(self, title)
+    }
+
+    fn set_visible(&mut self, visible: bool) -> crate::Result<()> {
+        TrayIcon::set_visible(self, visible)
+    }
+
+    fn is_visible(&self) -> bool {
+        TrayIcon::is_visible(self)
+    }
+
+    fn has_icon(&self) -> bool {
+        TrayIcon::has_icon(self)
+    }
+
+    fn has_menu(&self) -> bool {
+        TrayIcon::has_menu(self)
+    }
+
+    fn rect(&self) -> crate::Result<Option<crate::Rect>> {
+        TrayIcon::rect(self)
+    }
+}
+
+/// A [`TrayIcon`] that can be shared across threads.
+///
+/// Every access to the inner tray icon, including dropping it,
+/// happens on the thread that created it.
+#[cfg(feature = "arc")]
+pub(crate) struct SharedTrayIcon {
+    owner: OwnerThread,
+    tray: std::sync::Arc<OwnedTrayIcon>,
+}
+
+/// The tray icon of a [`SharedTrayIcon`].
+#[cfg(feature = "arc")]
+struct OwnedTrayIcon(std::sync::Mutex<Option<TrayIcon>>);
+
+// SAFETY: the tray icon is only accessed on its owner thread, from tasks given to
+// `run_on_owner_thread` or after checking `is_on_owner_thread`.
+#[cfg(feature = "arc")]
+unsafe impl Send for OwnedTrayIcon {}
+#[cfg(feature = "arc")]
+unsafe impl Sync for OwnedTrayIcon {}
+
+#[cfg(feature = "arc")]
+impl OwnedTrayIcon {
+    fn take(&self) -> Option<TrayIcon> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .take()
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut TrayIcon) -> R) -> R {
+        let mut tray = self
+            .0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        f(tray.as_mut().expect("tray icon was already dropped"))
+    }
+}
+
+#[cfg(feature = "arc")]
+impl SharedTrayIcon {
+    pub(crate) fn new(tray: TrayIcon) -> Self {
+        Self {
+            owner: tray.owner_thread(),
+            tray: std::sync::Arc::new(OwnedTrayIcon(std::sync::Mutex::new(Some(tray)))),
+        }
+    }
+
+    /// Runs `f` with the tray icon on its owner thread and blocks until it returns.
+    ///
+    /// Returns [`Error::OwnerThreadUnavailable`](crate::Error::OwnerThreadUnavailable)
+    /// if the owner thread isn't running its event loop.
+    pub(crate) fn with<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut TrayIcon) -> R + Send + 'static,
+    ) -> crate::Result<R> {
+        let tray = self.tray.clone();
+        run_on_owner_thread(self.owner, move || crate::defer_events(|| tray.with(f)))
+    }
+
+    /// Runs `f` with the tray icon if called on its owner thread, for arguments
+    /// which can't be sent to other threads, like menus.
+    ///
+    /// Returns [`Error::NotOwnerThread`](crate::Error::NotOwnerThread) otherwise.
+    pub(crate) fn with_local<R>(&self, f: impl FnOnce(&mut TrayIcon) -> R) -> crate::Result<R> {
+        if !is_on_owner_thread(self.owner) {
+            return Err(crate::Error::NotOwnerThread);
+        }
+        Ok(crate::defer_events(|| self.tray.with(f)))
+    }
+}
+
+#[cfg(feature = "arc")]
+impl Drop for SharedTrayIcon {
+    fn drop(&mut self) {
+        let tray = self.tray.clone();
+        if run_on_owner_thread(self.owner, move || drop(tray.take())).is_err() {
+            // dropping the tray icon on another thread isn't safe, leak it instead
+            std::mem::forget(self.tray.take());
+        }
+    }
+}
@@ -9,7 +9,8 @@ use std::{fmt, io, mem, path::Path, sync::Arc};
 use windows_sys::{
     core::PCWSTR,
     Win32::UI::WindowsAndMessaging::{
-        CreateIcon, DestroyIcon, LoadImageW, HICON, IMAGE_ICON, LR_DEFAULTSIZE, LR_LOADFROMFILE,
+        CopyImage, CreateIcon, DestroyIcon, LoadImageW, PrivateExtractIconsW, HICON, IMAGE_ICON,
+        LR_DEFAULTCOLOR, LR_DEFAULTSIZE, LR_LOADFROMFILE,
     },
 };
 
@@ -62,24 +63,74 @@ struct RaiiIcon {
 #[derive(Clone)]
 pub(crate) struct WinIcon {
     inner: Arc<RaiiIcon>,
+    /// The RGBA data this icon was created from, if any.
+    rgba: Option<Arc<RgbaIcon>>,
 }
 
 unsafe impl Send for WinIcon {}
+// SAFETY: the icon handle is never modified after creation, only read when showing
+// the icon and destroyed once the last reference is dropped.
+unsafe impl Sync for WinIcon {}
 
 impl WinIcon {
     pub fn as_raw_handle(&self) -> HICON {
         self.inner.handle
     }
 
+    /// Returns the dimensions of the icon, if it was created from RGBA data.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        self.rgba.as_ref().map(|rgba| (rgba.width, rgba.height))
+    }
+
     pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
         let rgba_icon = RgbaIcon::from_rgba(rgba, width, height)?;
-        rgba_icon.into_windows_icon()
+        let mut icon = rgba_icon.clone().into_windows_icon()?;
+        icon.rgba = Some(Arc::new(rgba_icon));
+        Ok(icon)
+    }
+
+    pub fn resize(&self, width: u32, height: u32) -> io::Result<Self> {
+        if let Some(rgba) = &self.rgba {
+            let to_io_error = |e: BadIcon| match e {
+                BadIcon::OsError(e) => e,
+                e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+            };
+            let resized = rgba.resize(width, height).map_err(to_io_error)?;
+            return Self::from_rgba(resized.rgba, width, height).map_err(to_io_error);
+        }
+
+        let handle = unsafe {
+            CopyImage(
+                self.as_raw_handle(),
+                IMAGE_ICON,
+                width as i32,
+                height as i32,
+                LR_DEFAULTCOLOR,
+            )
+        };
+        if !handle.is_null() {
+            Ok(WinIcon::from_handle(handle as HICON))
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    pub fn grayscale(&self) -> Result<Self, BadIcon> {
+        let rgba = self.rgba.as_ref().ok_or_else(|| {
+            BadIcon::OsError(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only icons created from RGBA data can be converted to grayscale",
+            ))
+        })?;
+        let gray = rgba.to_grayscale();
+        Self::from_rgba(gray.rgba, gray.width, gray.height)
     }
 
     pub(crate) fn from_handle(handle: HICON) -> Self {
         Self {
             #[allow(clippy::arc_with_non_send_sync)]
             inner: Arc::new(RaiiIcon { handle }),
+            rgba: None,
         }
     }
 
@@ -143,6 +194,37 @@ impl WinIcon {
         let wide_name = util::encode_wide(resource_name);
         Self::from_resource_inner_name(wide_name.as_ptr(), size)
     }
+
+    pub(crate) fn from_resource_at_size(
+        resource_id: u16,
+        size: (u32, u32),
+    ) -> Result<Self, BadIcon> {
+        let module_path = util::get_module_file_name().map_err(BadIcon::OsError)?;
+
+        let mut handle = std::ptr::null_mut();
+        let count = unsafe {
+            PrivateExtractIconsW(
+                module_path.as_ptr(),
+                // a negative index is interpreted as a resource id
+                -(resource_id as i32),
+                size.0 as i32,
+                size.1 as i32,
+                &mut handle,
+                std::ptr::null_mut(),
+                1,
+                0,
+            )
+        };
+        // returns 0xFFFFFFFF on failure and 0 if the icon was not found
+        if count == 0 || count == u32::MAX || handle.is_null() {
+            return Err(BadIcon::OsError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("failed to extract icon resource {resource_id} from the module"),
+            )));
+        }
+
+        Ok(WinIcon::from_handle(handle))
+    }
 }
 
 impl Drop for RaiiIcon {
@@ -4,27 +4,42 @@
 
 mod icon;
 mod util;
-use std::ptr;
+use std::{
+    collections::HashSet,
+    ptr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, PoisonError,
+    },
+};
 
 use once_cell::sync::Lazy;
 use windows_sys::{
+    core::GUID,
     s,
     Win32::{
-        Foundation::{FALSE, HWND, LPARAM, LRESULT, POINT, RECT, S_OK, TRUE, WPARAM},
+        Foundation::{
+            ERROR_SUCCESS, FALSE, HMODULE, HWND, LPARAM, LRESULT, POINT, RECT, S_OK, TRUE, WPARAM,
+        },
+        System::{
+            Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD},
+            Threading::GetCurrentThreadId,
+        },
         UI::{
             Shell::{
-                Shell_NotifyIconGetRect, Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP,
-                NIM_ADD, NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW, NOTIFYICONIDENTIFIER,
+                Shell_NotifyIconGetRect, Shell_NotifyIconW, NIF_GUID, NIF_ICON, NIF_MESSAGE,
+                NIF_TIP, NIM_ADD, NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW, NOTIFYICONIDENTIFIER,
             },
             WindowsAndMessaging::{
-                CreateWindowExW, DefWindowProcW, DestroyWindow, GetCursorPos, KillTimer,
-                RegisterClassW, RegisterWindowMessageA, SendMessageW, SetForegroundWindow,
-                SetTimer, TrackPopupMenu, CREATESTRUCTW, CW_USEDEFAULT, GWL_USERDATA, HICON, HMENU,
-                TPM_BOTTOMALIGN, TPM_LEFTALIGN, WM_CREATE, WM_DESTROY, WM_LBUTTONDBLCLK,
-                WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK, WM_MBUTTONDOWN, WM_MBUTTONUP,
-                WM_MOUSEMOVE, WM_NCCREATE, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN, WM_RBUTTONUP,
-                WM_TIMER, WNDCLASSW, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
-                WS_EX_TRANSPARENT, WS_OVERLAPPED,
+                CreateWindowExW, DefWindowProcW, DestroyWindow, GetCursorPos,
+                GetWindowThreadProcessId, KillTimer, PostMessageW, RegisterClassW,
+                RegisterWindowMessageA, SendMessageW, SetForegroundWindow, SetTimer,
+                TrackPopupMenu, CREATESTRUCTW, CW_USEDEFAULT, GWL_USERDATA, HICON, HMENU,
+                TPM_BOTTOMALIGN, TPM_LEFTALIGN, WM_CREATE, WM_DESTROY, WM_DISPLAYCHANGE,
+                WM_ENDSESSION, WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK,
+                WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEMOVE, WM_NCCREATE, WM_RBUTTONDBLCLK,
+                WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETTINGCHANGE, WM_TIMER, WNDCLASSW, WS_EX_LAYERED,
+                WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TRANSPARENT, WS_OVERLAPPED,
             },
         },
     },
@@ -39,63 +54,125 @@ pub(crate) use self::icon::WinIcon as PlatformIcon;
 
 const WM_USER_TRAYICON: u32 = 6002;
 const WM_USER_UPDATE_TRAYMENU: u32 = 6003;
-const WM_USER_UPDATE_TRAYICON: u32 = 6004;
 const WM_USER_SHOW_TRAYICON: u32 = 6005;
 const WM_USER_HIDE_TRAYICON: u32 = 6006;
 const WM_USER_UPDATE_TRAYTOOLTIP: u32 = 6007;
 const WM_USER_LEAVE_TIMER_ID: u32 = 6008;
 const WM_USER_SHOW_MENU_ON_LEFT_CLICK: u32 = 6009;
+#[cfg(feature = "arc")]
+const WM_USER_DISPATCH: u32 = 6010;
+const WM_USER_DESTROY_TRAYICON: u32 = 6011;
+const WM_USER_SHOW_MENU_ON_RIGHT_CLICK: u32 = 6012;
+const WM_USER_REFRESH_TRAYICON: u32 = 6013;
+const WM_USER_GET_TRAYRECT: u32 = 6014;
 /// When the taskbar is created, it registers a message with the "TaskbarCreated" string and then broadcasts this message to all top-level windows
 /// When the application receives this message, it should assume that any taskbar icons it added have been removed and add them again.
 static S_U_TASKBAR_RESTART: Lazy<u32> =
     Lazy::new(|| unsafe { RegisterWindowMessageA(s!("TaskbarCreated")) });
 
+/// The number of tray icons currently shown by this process.
+static ACTIVE_TRAY_ICONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Beyond this number of tray icons in a single process the shell behavior
+/// is undefined and some icons may not appear.
+#[cfg(feature = "log")]
+const MAX_ACTIVE_TRAY_ICONS: usize = 16;
+
+/// The module handles, as `usize`, that already registered a tray window class.
+static REGISTERED_WINDOW_CLASSES: Lazy<Mutex<HashSet<usize>>> = Lazy::new(Default::default);
+
 struct TrayUserData {
     internal_id: u32,
+    guid: Option<GUID>,
     id: TrayIconId,
     hwnd: HWND,
     hpopupmenu: Option<HMENU>,
     icon: Option<Icon>,
+    dark_mode_icon: Option<Icon>,
+    is_dark_mode: bool,
     tooltip: Option<String>,
     entered: bool,
     last_position: Option<PhysicalPosition<f64>>,
+    /// The rect of the tray icon as of the last mouse event, see [`TrayIcon::rect`].
+    last_rect: Option<RECT>,
     menu_on_left_click: bool,
+    menu_on_right_click: bool,
+    leave_detection_interval_ms: u32,
+    visible: bool,
+}
+
+impl TrayUserData {
+    /// The icon to show for the current theme.
+    fn shown_icon(&self) -> Option<&Icon> {
+        match &self.dark_mode_icon {
+            Some(icon) if self.is_dark_mode => Some(icon),
+            _ => self.icon.as_ref(),
+        }
+    }
+
+    /// Updates the shown icon.
+    unsafe fn refresh_icon(&self) -> std::io::Result<()> {
+        let mut nid = NOTIFYICONDATAW {
+            uFlags: NIF_ICON,
+            hWnd: self.hwnd,
+            uID: self.internal_id,
+            ..std::mem::zeroed()
+        };
+        set_nid_guid(&mut nid, self.guid);
+        if let Some(icon) = self.shown_icon() {
+            nid.hIcon = icon.inner.as_raw_handle();
+        }
+
+        if Shell_NotifyIconW(NIM_MODIFY, &mut nid as _) == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
 }
 
 pub struct TrayIcon {
+    id: TrayIconId,
     hwnd: HWND,
     menu: Option<Box<dyn menu::ContextMenu>>,
     internal_id: u32,
+    guid: Option<GUID>,
+    icon_size_hint: Option<(u32, u32)>,
+    /// The icon as it was set, before being scaled to `icon_size_hint`.
+    icon: Option<Icon>,
+    visible: bool,
 }
 
 impl TrayIcon {
     pub fn new(id: TrayIconId, attrs: TrayIconAttributes) -> crate::Result<Self> {
         let internal_id = COUNTER.next();
+        let guid = attrs.guid.map(|g| GUID::from_u128(u128::from_be_bytes(g)));
+        let icon = scale_icon(attrs.icon.clone(), attrs.icon_size_hint)?;
+        let dark_mode_icon = scale_icon(attrs.dark_mode_icon.clone(), attrs.icon_size_hint)?;
 
-        let class_name = util::encode_wide("tray_icon_app");
+        let hinstance = util::get_instance_handle();
+        let class_name = register_window_class(hinstance)?;
         unsafe {
-            let hinstance = util::get_instance_handle();
-
-            let wnd_class = WNDCLASSW {
-                lpfnWndProc: Some(tray_proc),
-                lpszClassName: class_name.as_ptr(),
-                hInstance: hinstance,
-                ..std::mem::zeroed()
-            };
-
-            RegisterClassW(&wnd_class);
-
             let traydata = TrayUserData {
-                id,
+                id: id.clone(),
                 internal_id,
+                guid,
                 hwnd: std::ptr::null_mut(),
                 hpopupmenu: attrs.menu.as_ref().map(|m| m.hpopupmenu() as _),
-                icon: attrs.icon.clone(),
+                icon,
+                dark_mode_icon,
+                is_dark_mode: is_dark_mode(),
                 tooltip: attrs.tooltip.clone(),
                 entered: false,
                 last_position: None,
+                last_rect: None,
                 menu_on_left_click: attrs.menu_on_left_click,
+                menu_on_right_click: attrs.menu_on_right_click,
+                leave_detection_interval_ms: attrs.leave_detection_interval_ms,
+                visible: attrs.visible,
             };
+            let hicon = traydata.shown_icon().map(|i| i.inner.as_raw_handle());
+            // freed in `WM_DESTROY` once the window is created
+            let traydata = Box::into_raw(Box::new(traydata));
 
             let hwnd = CreateWindowExW(
                 WS_EX_NOACTIVATE | WS_EX_TRANSPARENT | WS_EX_LAYERED |
@@ -117,60 +194,73 @@ impl TrayIcon {
                 std::ptr::null_mut(),
                 std::ptr::null_mut(),
                 hinstance,
-                Box::into_raw(Box::new(traydata)) as _,
+                traydata as _,
             );
             if hwnd.is_null() {
-                return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+                let error = std::io::Error::last_os_error();
+                // `WM_DESTROY` is only sent to windows that were created, so the data
+                // wasn't freed by the window proc
+                drop(Box::from_raw(traydata));
+                return Err(crate::Error::OsError(error));
             }
 
-            let hicon = attrs.icon.as_ref().map(|i| i.inner.as_raw_handle());
-
-            if !register_tray_icon(hwnd, internal_id, &hicon, &attrs.tooltip) {
-                return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+            if attrs.visible && !register_tray_icon(hwnd, internal_id, guid, &hicon, &attrs.tooltip)
+            {
+                let error = std::io::Error::last_os_error();
+                // frees the data in `WM_DESTROY`
+                DestroyWindow(hwnd);
+                return Err(crate::Error::OsError(error));
             }
 
             if let Some(menu) = &attrs.menu {
                 menu.attach_menu_subclass_for_hwnd(hwnd as _);
             }
 
+            let active_tray_icons = ACTIVE_TRAY_ICONS.fetch_add(1, Ordering::Relaxed) + 1;
+            #[cfg(feature = "log")]
+            if active_tray_icons > MAX_ACTIVE_TRAY_ICONS {
+                log::warn!(
+                    "{active_tray_icons} tray icons are active in this process, \
+                    tray icons beyond {MAX_ACTIVE_TRAY_ICONS} may not appear"
+                );
+            }
+            #[cfg(not(feature = "log"))]
+            let _ = active_tray_icons;
+
             Ok(Self {
+                id,
                 hwnd,
                 internal_id,
+                guid,
+                icon_size_hint: attrs.icon_size_hint,
                 menu: attrs.menu,
+                icon: attrs.icon,
+                visible: attrs.visible,
             })
         }
     }
 
-    pub fn set_icon(&mut self, icon: Option<Icon>) -> crate::Result<()> {
+    pub fn set_icon(&mut self, icon: Option<Icon>) -> crate::Result<Option<Icon>> {
+        let scaled_icon = scale_icon(icon.clone(), self.icon_size_hint)?;
+        // send the new icon to the subclass proc which stores it in the tray data and
+        // updates the shown icon in one step, so showing the tray icon again after it was
+        // hidden, or after the taskbar was re-created, always uses the latest icon
+        let mut update: (Option<Icon>, std::io::Result<()>) =
+            (scaled_icon, Err(std::io::ErrorKind::Other.into()));
         unsafe {
-            let mut nid = NOTIFYICONDATAW {
-                uFlags: NIF_ICON,
-                hWnd: self.hwnd,
-                uID: self.internal_id,
-                ..std::mem::zeroed()
-            };
-
-            if let Some(hicon) = icon.as_ref().map(|i| i.inner.as_raw_handle()) {
-                nid.hIcon = hicon;
-            }
-
-            if Shell_NotifyIconW(NIM_MODIFY, &mut nid as _) == 0 {
-                return Err(crate::Error::OsError(std::io::Error::last_os_error()));
-            }
-
-            // send the new icon to the subclass proc to store it in the tray data
             SendMessageW(
                 self.hwnd,
-                WM_USER_UPDATE_TRAYICON,
-                Box::into_raw(Box::new(icon)) as _,
+                WM_USER_REFRESH_TRAYICON,
+                &mut update as *mut (Option<Icon>, std::io::Result<()>) as _,
                 0,
             );
         }
+        update.1?;
 
-        Ok(())
+        Ok(std::mem::replace(&mut self.icon, icon))
     }
 
-    pub fn set_menu(&mut self, menu: Option<Box<dyn menu::ContextMenu>>) {
+    pub fn set_menu(&mut self, menu: Option<Box<dyn menu::ContextMenu>>) -> crate::Result<()> {
         // Safety: self.hwnd is valid as long as as the TrayIcon is
         if let Some(menu) = &self.menu {
             unsafe { menu.detach_menu_subclass_from_hwnd(self.hwnd as _) };
@@ -190,36 +280,25 @@ impl TrayIcon {
         }
 
         self.menu = menu;
+        Ok(())
     }
 
     pub fn set_tooltip<S: AsRef<str>>(&mut self, tooltip: Option<S>) -> crate::Result<()> {
+        // send the new tooltip to the subclass proc which updates the shown tooltip
+        // and stores it in the tray data, like the icon in `set_icon`
+        let mut update: (Option<String>, std::io::Result<()>) = (
+            tooltip.map(|t| t.as_ref().to_string()),
+            Err(std::io::ErrorKind::Other.into()),
+        );
         unsafe {
-            let mut nid = NOTIFYICONDATAW {
-                uFlags: NIF_TIP,
-                hWnd: self.hwnd,
-                uID: self.internal_id,
-                ..std::mem::zeroed()
-            };
-            if let Some(tooltip) = &tooltip {
-                let tip = util::encode_wide(tooltip.as_ref());
-                #[allow(clippy::manual_memcpy)]
-                for i in 0..tip.len().min(128) {
-                    nid.szTip[i] = tip[i];
-                }
-            }
-
-            if Shell_NotifyIconW(NIM_MODIFY, &mut nid as _) == 0 {
-                return Err(crate::Error::OsError(std::io::Error::last_os_error()));
-            }
-
-            // send the new tooltip to the subclass proc to store it in the tray data
             SendMessageW(
                 self.hwnd,
                 WM_USER_UPDATE_TRAYTOOLTIP,
-                Box::into_raw(Box::new(tooltip.map(|t| t.as_ref().to_string()))) as _,
+                &mut update as *mut (Option<String>, std::io::Result<()>) as _,
                 0,
             );
         }
+        update.1?;
 
         Ok(())
     }
@@ -235,51 +314,163 @@ impl TrayIcon {
         }
     }
 
-    pub fn set_title<S: AsRef<str>>(&mut self, _title: Option<S>) {}
-
-    pub fn set_visible(&mut self, visible: bool) -> crate::Result<()> {
+    pub fn set_show_menu_on_right_click(&mut self, enable: bool) {
         unsafe {
             SendMessageW(
                 self.hwnd,
-                if visible {
-                    WM_USER_SHOW_TRAYICON
-                } else {
-                    WM_USER_HIDE_TRAYICON
-                },
-                0,
+                WM_USER_SHOW_MENU_ON_RIGHT_CLICK,
+                enable as usize,
                 0,
             );
         }
+    }
 
+    pub fn set_title<S: AsRef<str>>(&mut self, _title: Option<S>) -> crate::Result<()> {
         Ok(())
     }
 
-    pub fn rect(&self) -> Option<Rect> {
-        get_tray_rect(self.internal_id, self.hwnd).map(Into::into)
+    pub fn set_visible(&mut self, visible: bool) -> crate::Result<()> {
+        // removing a tray icon which isn't registered with the shell fails
+        if visible == self.visible {
+            return Ok(());
+        }
+
+        unsafe {
+            if visible {
+                SendMessageW(self.hwnd, WM_USER_SHOW_TRAYICON, 0, 0);
+            } else if SendMessageW(self.hwnd, WM_USER_HIDE_TRAYICON, 0, 0) == 0 {
+                return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+            }
+        }
+
+        self.visible = visible;
+        Ok(())
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn has_icon(&self) -> bool {
+        self.icon.is_some()
+    }
+
+    pub fn has_menu(&self) -> bool {
+        self.menu.is_some()
+    }
+
+    pub fn rect(&self) -> crate::Result<Option<Rect>> {
+        // a hidden tray icon isn't registered with the shell, so it has no rect
+        if !self.visible {
+            return Ok(None);
+        }
+
+        let mut rect: std::io::Result<RECT> = Err(std::io::ErrorKind::Other.into());
+        // the subclass proc queries the rect and caches it for the following mouse move events
+        unsafe {
+            SendMessageW(
+                self.hwnd,
+                WM_USER_GET_TRAYRECT,
+                &mut rect as *mut std::io::Result<RECT> as _,
+                0,
+            );
+        }
+        Ok(Some(rect?.into()))
     }
 }
 
 impl Drop for TrayIcon {
     fn drop(&mut self) {
+        ACTIVE_TRAY_ICONS.fetch_sub(1, Ordering::Relaxed);
+
         unsafe {
-            remove_tray_icon(self.hwnd, self.internal_id);
+            // a hidden tray icon was already removed from the shell
+            if self.visible {
+                let result = remove_tray_icon(self.hwnd, self.internal_id, self.guid);
+                #[cfg(feature = "log")]
+                if let Err(e) = result {
+                    log::warn!("failed to remove tray icon: {e}");
+                }
+                #[cfg(not(feature = "log"))]
+                let _ = result;
+            }
 
             if let Some(menu) = &self.menu {
                 menu.detach_menu_subclass_from_hwnd(self.hwnd as _);
             }
 
-            // destroy the hidden window used by the tray
-            DestroyWindow(self.hwnd);
+            // destroy the hidden window used by the tray, `DestroyWindow` can only be called
+            // from the thread that created the window so otherwise let the window destroy itself
+            if is_owner_thread(self.hwnd) {
+                DestroyWindow(self.hwnd);
+            } else {
+                PostMessageW(self.hwnd, WM_USER_DESTROY_TRAYICON, 0, 0);
+            }
         }
+
+        super::send_destroyed(&self.id);
     }
 }
 
+#[cfg(feature = "arc")]
+impl TrayIcon {
+    pub(crate) fn owner_thread(&self) -> OwnerThread {
+        self.hwnd as _
+    }
+}
+
+/// The hidden window of the tray icon, which is owned by the thread that created it.
+#[cfg(feature = "arc")]
+pub(crate) type OwnerThread = isize;
+
+#[cfg(feature = "arc")]
+pub(crate) fn is_on_owner_thread(owner: OwnerThread) -> bool {
+    is_owner_thread(owner as HWND)
+}
+
+/// Runs `f` on the thread that created the tray icon and blocks until it returns,
+/// which requires that thread to be running its message loop.
+#[cfg(feature = "arc")]
+pub(crate) fn run_on_owner_thread<R: Send + 'static>(
+    owner: OwnerThread,
+    f: impl FnOnce() -> R + Send + 'static,
+) -> crate::Result<R> {
+    if is_on_owner_thread(owner) {
+        return Ok(f());
+    }
+
+    let mut f = Some(f);
+    let mut ret = None;
+    {
+        let mut task = || ret = f.take().map(|f| f());
+        let mut task: &mut dyn FnMut() = &mut task;
+        // SendMessageW blocks until the owner thread has processed the message,
+        // and returns without running the task if the window was destroyed
+        unsafe {
+            SendMessageW(
+                owner as HWND,
+                WM_USER_DISPATCH,
+                &mut task as *mut &mut dyn FnMut() as _,
+                0,
+            );
+        }
+    }
+    ret.ok_or(crate::Error::OwnerThreadUnavailable)
+}
+
 unsafe extern "system" fn tray_proc(
     hwnd: HWND,
     msg: u32,
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
+    #[cfg(feature = "arc")]
+    if msg == WM_USER_DISPATCH {
+        let task = &mut *(wparam as *mut &mut dyn FnMut());
+        task();
+        return 0;
+    }
+
     let userdata_ptr = unsafe { util::get_window_long(hwnd, GWL_USERDATA) };
     let userdata_ptr = match (userdata_ptr, msg) {
         (0, WM_NCCREATE) => {
@@ -299,46 +490,129 @@ unsafe extern "system" fn tray_proc(
 
     let userdata = &mut *(userdata_ptr);
 
+    // the tray icon may have moved
+    if matches!(msg, WM_DISPLAYCHANGE | WM_SETTINGCHANGE) {
+        userdata.last_rect = None;
+    }
+
     match msg {
         WM_DESTROY => {
             drop(Box::from_raw(userdata_ptr));
             return 0;
         }
+        WM_USER_DESTROY_TRAYICON => {
+            // userdata is freed in WM_DESTROY so it must not be accessed after this
+            DestroyWindow(hwnd);
+            return 0;
+        }
         WM_USER_UPDATE_TRAYMENU => {
             let hpopupmenu = Box::from_raw(wparam as *mut Option<isize>);
             userdata.hpopupmenu = (*hpopupmenu).map(|h| h as *mut _);
         }
-        WM_USER_UPDATE_TRAYICON => {
-            let icon = Box::from_raw(wparam as *mut Option<Icon>);
-            userdata.icon = *icon;
+        WM_USER_REFRESH_TRAYICON => {
+            let (icon, result) = &mut *(wparam as *mut (Option<Icon>, std::io::Result<()>));
+            let previous = std::mem::replace(&mut userdata.icon, icon.take());
+            // a hidden tray icon isn't registered with the shell,
+            // the new icon is shown when the tray icon is shown again
+            *result = if userdata.visible {
+                userdata.refresh_icon()
+            } else {
+                Ok(())
+            };
+            // keep the tray data in sync with `TrayIcon::icon`, which isn't updated on error
+            if result.is_err() {
+                userdata.icon = previous;
+            }
+            return 0;
+        }
+        WM_USER_GET_TRAYRECT => {
+            // the tray icon moves when other tray icons are added or removed, or when it is moved
+            // in or out of the overflow area, so always query the rect and refresh the cache
+            let rect = &mut *(wparam as *mut std::io::Result<RECT>);
+            *rect = get_tray_rect(userdata.internal_id, userdata.guid, hwnd);
+            userdata.last_rect = rect.as_ref().ok().copied();
+            return 0;
         }
         WM_USER_SHOW_TRAYICON => {
+            userdata.last_rect = None;
+            userdata.visible = true;
             register_tray_icon(
                 userdata.hwnd,
                 userdata.internal_id,
-                &userdata.icon.as_ref().map(|i| i.inner.as_raw_handle()),
+                userdata.guid,
+                &userdata.shown_icon().map(|i| i.inner.as_raw_handle()),
                 &userdata.tooltip,
             );
         }
         WM_USER_HIDE_TRAYICON => {
-            remove_tray_icon(userdata.hwnd, userdata.internal_id);
+            userdata.last_rect = None;
+            userdata.visible = false;
+            return remove_tray_icon(userdata.hwnd, userdata.internal_id, userdata.guid).is_ok()
+                as _;
+        }
+        // the session is ending and the process may be terminated at any point after this
+        // returns, so remove the icon now instead of leaving it until the shell cleans it up
+        WM_ENDSESSION if wparam != 0 => {
+            let _ = remove_tray_icon(userdata.hwnd, userdata.internal_id, userdata.guid);
+            return 0;
         }
         WM_USER_UPDATE_TRAYTOOLTIP => {
-            let tooltip = Box::from_raw(wparam as *mut Option<String>);
-            userdata.tooltip = *tooltip;
+            let (tooltip, result) = &mut *(wparam as *mut (Option<String>, std::io::Result<()>));
+            // like the icon, the tooltip of a hidden tray icon is shown when it is shown again
+            *result = if userdata.visible {
+                let mut nid = tooltip_nid(
+                    hwnd,
+                    userdata.internal_id,
+                    userdata.guid,
+                    tooltip.as_deref(),
+                );
+                if Shell_NotifyIconW(NIM_MODIFY, &mut nid as _) == 0 {
+                    Err(std::io::Error::last_os_error())
+                } else {
+                    Ok(())
+                }
+            } else {
+                Ok(())
+            };
+            if result.is_ok() {
+                userdata.tooltip = tooltip.take();
+            }
+            return 0;
         }
-        _ if msg == *S_U_TASKBAR_RESTART => {
-            remove_tray_icon(userdata.hwnd, userdata.internal_id);
+        // a hidden tray icon must stay hidden
+        _ if msg == *S_U_TASKBAR_RESTART && userdata.visible => {
+            // the icon is usually already gone when the taskbar is re-created
+            userdata.last_rect = None;
+            let _ = remove_tray_icon(userdata.hwnd, userdata.internal_id, userdata.guid);
             register_tray_icon(
                 userdata.hwnd,
                 userdata.internal_id,
-                &userdata.icon.as_ref().map(|i| i.inner.as_raw_handle()),
+                userdata.guid,
+                &userdata.shown_icon().map(|i| i.inner.as_raw_handle()),
                 &userdata.tooltip,
             );
         }
         WM_USER_SHOW_MENU_ON_LEFT_CLICK => {
             userdata.menu_on_left_click = wparam != 0;
         }
+        WM_USER_SHOW_MENU_ON_RIGHT_CLICK => {
+            userdata.menu_on_right_click = wparam != 0;
+        }
+
+        // sent when the user switches between light and dark mode
+        WM_SETTINGCHANGE
+            if lparam != 0 && util::decode_wide(lparam as *const u16) == "ImmersiveColorSet" =>
+        {
+            userdata.is_dark_mode = is_dark_mode();
+            if userdata.dark_mode_icon.is_some() && userdata.visible {
+                let _ = userdata.refresh_icon();
+            }
+
+            TrayIconEvent::send(TrayIconEvent::ThemeChanged {
+                id: userdata.id.clone(),
+                is_dark_mode: userdata.is_dark_mode,
+            });
+        }
 
         WM_USER_TRAYICON
             if matches!(
@@ -362,17 +636,26 @@ unsafe extern "system" fn tray_proc(
 
             let id = userdata.id.clone();
             let position = PhysicalPosition::new(cursor.x as f64, cursor.y as f64);
-
-            let rect = match get_tray_rect(userdata.internal_id, hwnd) {
-                Some(rect) => Rect::from(rect),
-                None => return 0,
+            let scale_factor = util::scale_factor_at(cursor);
+
+            // move events are by far the most frequent, and the tray icon is unlikely
+            // to move while hovered, so reuse the rect cached by the previous events
+            let rect = match userdata.last_rect {
+                Some(rect) if userdata.entered && lparam as u32 == WM_MOUSEMOVE => rect,
+                _ => match get_tray_rect(userdata.internal_id, userdata.guid, hwnd) {
+                    Ok(rect) => rect,
+                    Err(_) => return 0,
+                },
             };
+            userdata.last_rect = Some(rect);
+            let rect = Rect::from(rect);
 
             let event = match lparam as u32 {
                 WM_LBUTTONDOWN => TrayIconEvent::Click {
                     id,
                     rect,
                     position,
+                    scale_factor,
                     button: MouseButton::Left,
                     button_state: MouseButtonState::Down,
                 },
@@ -380,6 +663,7 @@ unsafe extern "system" fn tray_proc(
                     id,
                     rect,
                     position,
+                    scale_factor,
                     button: MouseButton::Right,
                     button_state: MouseButtonState::Down,
                 },
@@ -387,6 +671,7 @@ unsafe extern "system" fn tray_proc(
                     id,
                     rect,
                     position,
+                    scale_factor,
                     button: MouseButton::Middle,
                     button_state: MouseButtonState::Down,
                 },
@@ -394,6 +679,7 @@ unsafe extern "system" fn tray_proc(
                     id,
                     rect,
                     position,
+                    scale_factor,
                     button: MouseButton::Left,
                     button_state: MouseButtonState::Up,
                 },
@@ -401,6 +687,7 @@ unsafe extern "system" fn tray_proc(
                     id,
                     rect,
                     position,
+                    scale_factor,
                     button: MouseButton::Right,
                     button_state: MouseButtonState::Up,
                 },
@@ -408,6 +695,7 @@ unsafe extern "system" fn tray_proc(
                     id,
                     rect,
                     position,
+                    scale_factor,
                     button: MouseButton::Middle,
                     button_state: MouseButtonState::Up,
                 },
@@ -415,23 +703,31 @@ unsafe extern "system" fn tray_proc(
                     id,
                     rect,
                     position,
+                    scale_factor,
                     button: MouseButton::Left,
                 },
                 WM_RBUTTONDBLCLK => TrayIconEvent::DoubleClick {
                     id,
                     rect,
                     position,
+                    scale_factor,
                     button: MouseButton::Right,
                 },
                 WM_MBUTTONDBLCLK => TrayIconEvent::DoubleClick {
                     id,
                     rect,
                     position,
+                    scale_factor,
                     button: MouseButton::Middle,
                 },
                 WM_MOUSEMOVE if !userdata.entered => {
                     userdata.entered = true;
-                    TrayIconEvent::Enter { id, rect, position }
+                    TrayIconEvent::Enter {
+                        id,
+                        rect,
+                        position,
+                        scale_factor,
+                    }
                 }
                 WM_MOUSEMOVE if userdata.entered => {
                     // handle extra WM_MOUSEMOVE events, ignore if position hasn't changed
@@ -439,9 +735,19 @@ unsafe extern "system" fn tray_proc(
                     userdata.last_position = Some(position);
                     if cursor_moved {
                         // Set or update existing timer, where we check if cursor left
-                        SetTimer(hwnd, WM_USER_LEAVE_TIMER_ID as _, 15, Some(tray_timer_proc));
-
-                        TrayIconEvent::Move { id, rect, position }
+                        SetTimer(
+                            hwnd,
+                            WM_USER_LEAVE_TIMER_ID as _,
+                            userdata.leave_detection_interval_ms,
+                            Some(tray_timer_proc),
+                        );
+
+                        TrayIconEvent::Move {
+                            id,
+                            rect,
+                            position,
+                            scale_factor,
+                        }
                     } else {
                         return 0;
                     }
@@ -452,7 +758,7 @@ unsafe extern "system" fn tray_proc(
 
             TrayIconEvent::send(event);
 
-            if lparam as u32 == WM_RBUTTONDOWN
+            if (userdata.menu_on_right_click && lparam as u32 == WM_RBUTTONDOWN)
                 || (userdata.menu_on_left_click && lparam as u32 == WM_LBUTTONDOWN)
             {
                 if let Some(menu) = userdata.hpopupmenu {
@@ -468,10 +774,11 @@ unsafe extern "system" fn tray_proc(
                     return 0;
                 }
 
-                let rect = match get_tray_rect(userdata.internal_id, hwnd) {
-                    Some(r) => r,
-                    None => return 0,
+                let rect = match get_tray_rect(userdata.internal_id, userdata.guid, hwnd) {
+                    Ok(r) => r,
+                    Err(_) => return 0,
                 };
+                userdata.last_rect = Some(rect);
 
                 let in_x = (rect.left..rect.right).contains(&cursor.x);
                 let in_y = (rect.top..rect.bottom).contains(&cursor.y);
@@ -484,6 +791,10 @@ unsafe extern "system" fn tray_proc(
                         id: userdata.id.clone(),
                         rect: rect.into(),
                         position,
+                        scale_factor: util::scale_factor_at(POINT {
+                            x: rect.left,
+                            y: rect.top,
+                        }),
                     });
                 }
             }
@@ -501,6 +812,75 @@ unsafe extern "system" fn tray_timer_proc(hwnd: HWND, msg: u32, wparam: WPARAM,
     tray_proc(hwnd, msg, wparam, lparam as _);
 }
 
+pub fn init() -> crate::Result<()> {
+    Ok(())
+}
+
+/// Registers the tray window class for `hinstance`, if not already registered, and returns its name.
+///
+/// Each module gets its own class name so tray icons created from different DLLs
+/// don't conflict with each other.
+fn register_window_class(hinstance: HMODULE) -> crate::Result<Vec<u16>> {
+    let class_name = util::encode_wide(format!("tray_icon_app_{:x}", hinstance as usize));
+
+    let mut registered = REGISTERED_WINDOW_CLASSES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if !registered.contains(&(hinstance as usize)) {
+        let wnd_class = WNDCLASSW {
+            lpfnWndProc: Some(tray_proc),
+            lpszClassName: class_name.as_ptr(),
+            hInstance: hinstance,
+            ..unsafe { std::mem::zeroed() }
+        };
+
+        if unsafe { RegisterClassW(&wnd_class) } == 0 {
+            return Err(crate::Error::OsError(std::io::Error::last_os_error()));
+        }
+        registered.insert(hinstance as usize);
+    }
+
+    Ok(class_name)
+}
+
+/// Whether the current thread is the thread that created `hwnd`.
+#[inline]
+fn is_owner_thread(hwnd: HWND) -> bool {
+    unsafe { GetWindowThreadProcessId(hwnd, std::ptr::null_mut()) == GetCurrentThreadId() }
+}
+
+/// Scales the icon to `size_hint` if provided.
+fn scale_icon(icon: Option<Icon>, size_hint: Option<(u32, u32)>) -> crate::Result<Option<Icon>> {
+    match (icon, size_hint) {
+        (Some(icon), Some((width, height))) => Ok(Some(Icon {
+            inner: icon.inner.resize(width, height)?,
+        })),
+        (icon, _) => Ok(icon),
+    }
+}
+
+/// Whether the taskbar, and so the tray icons, are using the dark theme.
+fn is_dark_mode() -> bool {
+    let subkey =
+        util::encode_wide("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize");
+    let value = util::encode_wide("SystemUsesLightTheme");
+    let mut data: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            subkey.as_ptr(),
+            value.as_ptr(),
+            RRF_RT_REG_DWORD,
+            std::ptr::null_mut(),
+            &mut data as *mut u32 as _,
+            &mut size,
+        )
+    };
+    // the value is missing on Windows versions without a dark theme
+    status == ERROR_SUCCESS && data == 0
+}
+
 #[inline]
 unsafe fn show_tray_menu(hwnd: HWND, menu: HMENU, x: i32, y: i32) {
     // bring the hidden window to the foreground so the pop up menu
@@ -522,12 +902,13 @@ unsafe fn show_tray_menu(hwnd: HWND, menu: HMENU, x: i32, y: i32) {
 unsafe fn register_tray_icon(
     hwnd: HWND,
     tray_id: u32,
+    guid: Option<GUID>,
     hicon: &Option<HICON>,
     tooltip: &Option<String>,
 ) -> bool {
     let mut h_icon = std::ptr::null_mut();
     let mut flags = NIF_MESSAGE;
-    let mut sz_tip: [u16; 128] = [0; 128];
+    let mut sz_tip: [u16; TOOLTIP_MAX_LEN + 1] = [0; TOOLTIP_MAX_LEN + 1];
 
     if let Some(hicon) = hicon {
         flags |= NIF_ICON;
@@ -536,11 +917,7 @@ unsafe fn register_tray_icon(
 
     if let Some(tooltip) = tooltip {
         flags |= NIF_TIP;
-        let tip = util::encode_wide(tooltip);
-        #[allow(clippy::manual_memcpy)]
-        for i in 0..tip.len().min(128) {
-            sz_tip[i] = tip[i];
-        }
+        sz_tip = encode_tooltip(tooltip);
     }
 
     let mut nid = NOTIFYICONDATAW {
@@ -552,31 +929,86 @@ unsafe fn register_tray_icon(
         szTip: sz_tip,
         ..std::mem::zeroed()
     };
+    set_nid_guid(&mut nid, guid);
 
     Shell_NotifyIconW(NIM_ADD, &mut nid as _) == TRUE
 }
 
 #[inline]
-unsafe fn remove_tray_icon(hwnd: HWND, id: u32) {
+unsafe fn remove_tray_icon(hwnd: HWND, id: u32, guid: Option<GUID>) -> std::io::Result<()> {
     let mut nid = NOTIFYICONDATAW {
         uFlags: NIF_ICON,
         hWnd: hwnd,
         uID: id,
         ..std::mem::zeroed()
     };
+    set_nid_guid(&mut nid, guid);
 
     if Shell_NotifyIconW(NIM_DELETE, &mut nid as _) == FALSE {
-        eprintln!("Error removing system tray icon");
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// The maximum tooltip length in UTF-16 code units, excluding the null terminator.
+const TOOLTIP_MAX_LEN: usize = 127;
+
+/// Encodes `tooltip` as a null-terminated wide string for `NOTIFYICONDATAW::szTip`,
+/// truncating it to [`TOOLTIP_MAX_LEN`] code units without splitting a surrogate pair.
+fn encode_tooltip(tooltip: &str) -> [u16; TOOLTIP_MAX_LEN + 1] {
+    let mut sz_tip = [0; TOOLTIP_MAX_LEN + 1];
+    let mut len = 0;
+    for c in tooltip.chars() {
+        let c_len = c.len_utf16();
+        if len + c_len > TOOLTIP_MAX_LEN {
+            #[cfg(feature = "log")]
+            log::warn!(
+                "tray icon tooltip is longer than {TOOLTIP_MAX_LEN} UTF-16 code units and was truncated"
+            );
+            break;
+        }
+        c.encode_utf16(&mut sz_tip[len..len + c_len]);
+        len += c_len;
+    }
+    sz_tip
+}
+
+/// Identifies the icon by `guid` instead of `uID` when one is provided.
+///
+/// When `NIF_GUID` is set, `cbSize` must be set to the full struct size
+/// so the shell knows `guidItem` is present.
+#[inline]
+fn set_nid_guid(nid: &mut NOTIFYICONDATAW, guid: Option<GUID>) {
+    if let Some(guid) = guid {
+        nid.cbSize = std::mem::size_of::<NOTIFYICONDATAW>() as _;
+        nid.uFlags |= NIF_GUID;
+        nid.guidItem = guid;
     }
 }
 
+/// Builds the data to update the tooltip of a tray icon, an empty tooltip clears it.
+fn tooltip_nid(hwnd: HWND, id: u32, guid: Option<GUID>, tooltip: Option<&str>) -> NOTIFYICONDATAW {
+    let mut nid = NOTIFYICONDATAW {
+        uFlags: NIF_TIP,
+        hWnd: hwnd,
+        uID: id,
+        ..unsafe { std::mem::zeroed() }
+    };
+    set_nid_guid(&mut nid, guid);
+    if let Some(tooltip) = tooltip {
+        nid.szTip = encode_tooltip(tooltip);
+    }
+    nid
+}
+
 #[inline]
-fn get_tray_rect(id: u32, hwnd: HWND) -> Option<RECT> {
+fn get_tray_rect(id: u32, guid: Option<GUID>, hwnd: HWND) -> std::io::Result<RECT> {
     let nid = NOTIFYICONIDENTIFIER {
         hWnd: hwnd,
         cbSize: std::mem::size_of::<NOTIFYICONIDENTIFIER>() as _,
         uID: id,
-        ..unsafe { std::mem::zeroed() }
+        guidItem: guid.unwrap_or(unsafe { std::mem::zeroed() }),
     };
 
     let mut rect = RECT {
@@ -585,10 +1017,11 @@ fn get_tray_rect(id: u32, hwnd: HWND) -> Option<RECT> {
         right: 0,
         top: 0,
     };
-    if unsafe { Shell_NotifyIconGetRect(&nid, &mut rect) } == S_OK {
-        Some(rect)
+    let hr = unsafe { Shell_NotifyIconGetRect(&nid, &mut rect) };
+    if hr == S_OK {
+        Ok(rect)
     } else {
-        None
+        Err(std::io::Error::from_raw_os_error(hr))
     }
 }
 
@@ -603,3 +1036,44 @@ impl From<RECT> for Rect {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_tooltip, tooltip_nid, NIF_TIP, TOOLTIP_MAX_LEN};
+
+    #[test]
+    fn tooltip_is_truncated_and_null_terminated() {
+        let tip = encode_tooltip(&"a".repeat(200));
+        assert!(tip[..TOOLTIP_MAX_LEN].iter().all(|&c| c == 'a' as u16));
+        assert_eq!(tip[TOOLTIP_MAX_LEN], 0);
+
+        // a surrogate pair that doesn't fit is dropped entirely
+        let tip = encode_tooltip(&format!("{}😀", "a".repeat(TOOLTIP_MAX_LEN - 1)));
+        assert_eq!(tip[TOOLTIP_MAX_LEN - 1], 0);
+    }
+
+    #[test]
+    fn clearing_tooltip_sends_empty_tip() {
+        let nid = tooltip_nid(std::ptr::null_mut(), 1, None, Some("tooltip"));
+        assert_eq!(nid.szTip[0], 't' as u16);
+
+        let nid = tooltip_nid(std::ptr::null_mut(), 1, None, None);
+        assert_ne!(nid.uFlags & NIF_TIP, 0);
+        assert_eq!(nid.szTip[0], 0);
+    }
+
+    #[test]
+    fn builds_hidden_and_tracks_visibility() {
+        let tray = crate::TrayIconBuilder::new()
+            .with_visible(false)
+            .build()
+            .unwrap();
+        assert!(!tray.is_visible());
+
+        tray.set_visible(true).unwrap();
+        assert!(tray.is_visible());
+
+        tray.set_visible(false).unwrap();
+        assert!(!tray.is_visible());
+    }
+}
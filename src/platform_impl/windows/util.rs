@@ -5,8 +5,12 @@
 use std::ops::{Deref, DerefMut};
 
 use windows_sys::Win32::{
-    Foundation::HWND,
-    UI::WindowsAndMessaging::{ACCEL, WINDOW_LONG_PTR_INDEX},
+    Foundation::{HWND, POINT, S_OK},
+    Graphics::Gdi::{MonitorFromPoint, MONITOR_DEFAULTTONEAREST},
+    UI::{
+        HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI},
+        WindowsAndMessaging::{ACCEL, USER_DEFAULT_SCREEN_DPI, WINDOW_LONG_PTR_INDEX},
+    },
 };
 
 pub fn encode_wide<S: AsRef<std::ffi::OsStr>>(string: S) -> Vec<u16> {
@@ -15,6 +19,19 @@ pub fn encode_wide<S: AsRef<std::ffi::OsStr>>(string: S) -> Vec<u16> {
         .collect()
 }
 
+/// Decodes a null-terminated wide string.
+///
+/// # Safety
+///
+/// `ptr` must be non-null and point to a null-terminated wide string.
+pub unsafe fn decode_wide(ptr: *const u16) -> String {
+    let mut len = 0;
+    while *ptr.add(len) != 0 {
+        len += 1;
+    }
+    String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len))
+}
+
 /// ACCEL wrapper to implement Debug
 #[derive(Clone)]
 #[repr(transparent)]
@@ -54,6 +71,9 @@ pub fn get_instance_handle() -> windows_sys::Win32::Foundation::HMODULE {
     // This is preferred over GetModuleHandle(NULL) because it also works in DLLs:
     // https://stackoverflow.com/questions/21718027/getmodulehandlenull-vs-hinstance
 
+    // The handle is never null: `__ImageBase` is resolved by the linker to the base address
+    // the module is loaded at, and a reference to it can't be null.
+
     extern "C" {
         static __ImageBase: windows_sys::Win32::System::SystemServices::IMAGE_DOS_HEADER;
     }
@@ -61,6 +81,52 @@ pub fn get_instance_handle() -> windows_sys::Win32::Foundation::HMODULE {
     unsafe { &__ImageBase as *const _ as _ }
 }
 
+/// Returns the null-terminated path of the module (executable or library)
+/// containing this code.
+pub fn get_module_file_name() -> std::io::Result<Vec<u16>> {
+    let mut path = vec![0u16; windows_sys::Win32::Foundation::MAX_PATH as usize];
+    loop {
+        let len = unsafe {
+            windows_sys::Win32::System::LibraryLoader::GetModuleFileNameW(
+                get_instance_handle(),
+                path.as_mut_ptr(),
+                path.len() as u32,
+            )
+        } as usize;
+
+        if len == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        // the path was truncated, try again with a larger buffer
+        if len == path.len() {
+            path.resize(path.len() * 2, 0);
+            continue;
+        }
+
+        path.truncate(len + 1);
+        return Ok(path);
+    }
+}
+
+/// Returns the scale factor of the monitor nearest to `point`.
+pub fn scale_factor_at(point: POINT) -> f64 {
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    let hr = unsafe {
+        GetDpiForMonitor(
+            MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST),
+            MDT_EFFECTIVE_DPI,
+            &mut dpi_x,
+            &mut dpi_y,
+        )
+    };
+    if hr == S_OK {
+        dpi_x as f64 / USER_DEFAULT_SCREEN_DPI as f64
+    } else {
+        1.0
+    }
+}
+
 #[inline(always)]
 pub unsafe fn get_window_long(hwnd: HWND, nindex: WINDOW_LONG_PTR_INDEX) -> isize {
     #[cfg(target_pointer_width = "64")]
@@ -87,3 +153,29 @@ pub unsafe fn set_window_long(
             as isize
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, os::windows::ffi::OsStringExt};
+
+    use super::*;
+
+    #[test]
+    fn encode_wide_is_null_terminated() {
+        assert_eq!(encode_wide(""), [0]);
+        assert_eq!(encode_wide("tray"), [116, 114, 97, 121, 0]);
+    }
+
+    #[test]
+    fn encode_wide_roundtrips() {
+        for string in ["tray-icon", "héllo wörld", "トレイ", "🦀 tray"] {
+            let wide = encode_wide(string);
+            let (last, units) = wide.split_last().unwrap();
+
+            assert_eq!(*last, 0);
+            assert_eq!(units, string.encode_utf16().collect::<Vec<_>>());
+            assert_eq!(OsString::from_wide(units), string);
+            assert_eq!(unsafe { decode_wide(wide.as_ptr()) }, string);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2022-2022 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Helpers for testing applications that use tray icons, available with the `testing` feature.
+
+use crate::TrayIconEvent;
+
+/// Injects a synthetic `event` as if it was triggered by a tray icon.
+///
+/// The event is delivered like any other event, either to the handler set with
+/// [`TrayIconEvent::set_event_handler`] or to the channel associated with [`TrayIconEvent::receiver`].
+pub fn inject_event(event: TrayIconEvent) {
+    TrayIconEvent::send(event);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{dpi, Rect, TrayIconEvent, TrayIconId};
+
+    #[test]
+    fn injects_event() {
+        // Observe the event through a handler rather than draining the global channel,
+        // which would consume events sent by other tests running concurrently.
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let previous = TrayIconEvent::get_event_handler();
+        TrayIconEvent::set_event_handler(Some({
+            let received = received.clone();
+            Arc::new(move |event: TrayIconEvent| {
+                if event.source_id() == "injected" {
+                    received.lock().unwrap().push(event);
+                }
+            })
+        }));
+
+        let event = TrayIconEvent::Enter {
+            id: TrayIconId::new("injected"),
+            position: dpi::PhysicalPosition::default(),
+            rect: Rect::default(),
+            scale_factor: 1.0,
+        };
+        super::inject_event(event.clone());
+
+        TrayIconEvent::set_event_handler(previous);
+        assert_eq!(*received.lock().unwrap(), [event]);
+    }
+}
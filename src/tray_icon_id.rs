@@ -1,14 +1,49 @@
-use std::{convert::Infallible, str::FromStr};
+use std::{
+    borrow::{Borrow, Cow},
+    convert::Infallible,
+    str::FromStr,
+};
 
 /// An unique id that is associated with a tray icon.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct TrayIconId(pub String);
+pub struct TrayIconId(pub Cow<'static, str>);
 
 impl TrayIconId {
     /// Create a new tray icon id.
     pub fn new<S: AsRef<str>>(id: S) -> Self {
-        Self(id.as_ref().to_string())
+        Self(Cow::Owned(id.as_ref().to_string()))
+    }
+
+    /// Create a new tray icon id from a static string, without allocating.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use tray_icon::TrayIconId;
+    /// const MAIN_TRAY: TrayIconId = TrayIconId::from_static("main");
+    /// assert_eq!(MAIN_TRAY, "main");
+    /// ```
+    pub const fn from_static(id: &'static str) -> Self {
+        Self(Cow::Borrowed(id))
+    }
+
+    /// Create a tray icon id from the bundle identifier of the application, e.g. `com.example.app`,
+    /// which stays the same across restarts. **macOS only**.
+    ///
+    /// Returns `None` if the application isn't bundled.
+    #[cfg(target_os = "macos")]
+    pub fn from_bundle_id() -> Option<Self> {
+        let bundle_id = unsafe { objc2_foundation::NSBundle::mainBundle().bundleIdentifier() }?;
+        Some(Self(Cow::Owned(bundle_id.to_string())))
+    }
+
+    /// Returns whether this id looks like one generated by [`TrayIconBuilder::new`](crate::TrayIconBuilder::new)
+    /// or [`TrayIcon::new`](crate::TrayIcon::new), that is, a non-empty string of ASCII digits.
+    ///
+    /// Note that application-defined numeric ids are also reported as auto-generated.
+    pub fn is_auto_generated(&self) -> bool {
+        !self.0.is_empty() && self.0.bytes().all(|b| b.is_ascii_digit())
     }
 }
 
@@ -18,12 +53,40 @@ impl AsRef<str> for TrayIconId {
     }
 }
 
+impl Borrow<str> for TrayIconId {
+    fn borrow(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
 impl<T: ToString> From<T> for TrayIconId {
     fn from(value: T) -> Self {
         Self::new(value.to_string())
     }
 }
 
+/// Parses the id as a UUID, which requires the `uuid` feature.
+///
+/// A UUID can be converted to an id with [`From`] like any other [`ToString`] type.
+///
+/// ## Example
+///
+/// ```
+/// # use tray_icon::TrayIconId;
+/// let uuid = uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+/// let id = TrayIconId::from(uuid);
+/// assert_eq!(uuid::Uuid::try_from(id).unwrap(), uuid);
+/// assert!(uuid::Uuid::try_from(TrayIconId::new("main")).is_err());
+/// ```
+#[cfg(feature = "uuid")]
+impl TryFrom<TrayIconId> for uuid::Uuid {
+    type Error = uuid::Error;
+
+    fn try_from(id: TrayIconId) -> std::result::Result<Self, Self::Error> {
+        uuid::Uuid::parse_str(&id.0)
+    }
+}
+
 impl FromStr for TrayIconId {
     type Err = Infallible;
 
@@ -82,4 +145,38 @@ mod test {
         assert_eq!(&TrayIconId::new("t"), &TrayIconId::new("t"));
         assert_eq!(TrayIconId::new("t").as_ref(), "t");
     }
+
+    #[test]
+    fn hash_map_lookup_by_str() {
+        let mut map = std::collections::HashMap::new();
+        map.insert(TrayIconId::new("myapp"), 1);
+        assert_eq!(map.get("myapp"), Some(&1));
+        assert_eq!(map.get("other"), None);
+    }
+
+    #[test]
+    fn from_static() {
+        const ID: TrayIconId = TrayIconId::from_static("static");
+        assert_eq!(ID, TrayIconId::new("static"));
+        assert!(matches!(ID.0, std::borrow::Cow::Borrowed(_)));
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid_roundtrip() {
+        let uuid = uuid::Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+        let id = TrayIconId::from(uuid);
+        assert_eq!(id, "01234567-89ab-cdef-0123-456789abcdef");
+        assert_eq!(uuid::Uuid::try_from(id).unwrap(), uuid);
+        assert!(uuid::Uuid::try_from(TrayIconId::new("main")).is_err());
+    }
+
+    #[test]
+    fn is_auto_generated() {
+        assert!(TrayIconId::new("1").is_auto_generated());
+        assert!(TrayIconId::new("42").is_auto_generated());
+        assert!(!TrayIconId::new("").is_auto_generated());
+        assert!(!TrayIconId::new("main").is_auto_generated());
+        assert!(!TrayIconId::new("-1").is_auto_generated());
+    }
 }